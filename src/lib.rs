@@ -0,0 +1,4 @@
+pub mod storage;
+
+#[cfg(test)]
+pub mod tests;
@@ -1,6 +1,3 @@
-mod storage;
-mod tests;
-
 fn main() {
     println!("Hello, world!");
 }
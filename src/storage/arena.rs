@@ -0,0 +1,65 @@
+use super::b_tree::BNode;
+
+// 默认最多缓存的 buffer 数量，插入时每一层最多同时需要 4 个临时节点
+pub const DEFAULT_ARENA_CAPACITY: usize = 32;
+
+// 可复用的节点 buffer 池
+// 插入和分裂过程中的临时节点从这里取出，写入 page 之后归还，
+// 避免每次修改都重新分配 page 大小的内存
+#[derive(Debug)]
+pub struct NodeArena {
+    free: Vec<Vec<u8>>,
+    capacity: usize,
+    hits: u64,
+    misses: u64,
+}
+
+impl Default for NodeArena {
+    fn default() -> Self {
+        Self::new(DEFAULT_ARENA_CAPACITY)
+    }
+}
+
+impl NodeArena {
+    // capacity 为 0 时不缓存，每次都重新分配
+    pub fn new(capacity: usize) -> Self {
+        NodeArena {
+            free: Vec::with_capacity(capacity),
+            capacity,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    // 取出一个 size 字节、内容全为 0 的节点
+    pub fn take(&mut self, size: usize) -> BNode {
+        let pos = self.free.iter().position(|buf| buf.capacity() >= size);
+        let Some(pos) = pos else {
+            self.misses += 1;
+            return BNode::new(size);
+        };
+
+        self.hits += 1;
+        let mut data = self.free.swap_remove(pos);
+        data.clear();
+        data.resize(size, 0);
+        BNode { data }
+    }
+
+    // 归还不再使用的节点，池已满时直接释放
+    pub fn put(&mut self, node: BNode) {
+        if node.data.capacity() > 0 && self.free.len() < self.capacity {
+            self.free.push(node.data);
+        }
+    }
+
+    // 从池中取到 buffer 的次数
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    // 池中没有合适的 buffer 而重新分配的次数
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+}
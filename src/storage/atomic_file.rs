@@ -0,0 +1,43 @@
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+
+// 先写入临时文件并 fsync，再 rename 覆盖 path，出错时删除临时文件
+// 这样 path 要么是旧的内容，要么是完整的新内容。最后 fsync 所在的目录，返回之后 rename 已经持久化
+pub fn write_atomic(
+    path: &Path,
+    write: impl FnOnce(&mut File) -> io::Result<()>,
+) -> io::Result<()> {
+    let tmp = tmp_path(path);
+
+    let result = File::create(&tmp).and_then(|mut fp| {
+        write(&mut fp)?;
+        fp.sync_all()
+    });
+    match result.and_then(|_| fs::rename(&tmp, path)) {
+        Ok(_) => sync_dir(path),
+        Err(err) => {
+            let _ = fs::remove_file(&tmp);
+            Err(err)
+        }
+    }
+}
+
+// rename 修改的是目录项，崩溃时只有 fsync 过的目录才能保证 rename 不丢失
+fn sync_dir(path: &Path) -> io::Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    File::open(dir)?.sync_all()
+}
+
+// 临时文件与 path 在同一个目录下，rename 才是原子的
+// 名字只取决于 path 和进程号，崩溃后遗留的临时文件会在下一次写入时被覆盖
+pub fn tmp_path(path: &Path) -> PathBuf {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(format!(".tmp.{}", std::process::id()));
+    PathBuf::from(tmp)
+}
@@ -1,24 +1,161 @@
-use std::{cmp::Ordering, io::Bytes, ops::Range, u16, u64};
+use std::{cmp::Ordering, collections::HashMap, ops::Bound, sync::Arc};
 
-const HEADER: usize = 4;
+use super::{
+    arena::NodeArena,
+    codec::{ValueCodec, VAL_COMPRESSED, VAL_RAW},
+    comparator::{ByteOrder, KeyComparator},
+    error::BTreeError,
+    overflow::{OVERFLOW_REF_SIZE, VAL_OVERFLOW},
+    page_store::{PageStore, StoreStats},
+    scan::ScanIter,
+    transaction::TxnState,
+};
 
-const BTREE_PAGE_SIZE: usize = 4096;
-const BTREE_MAX_KEY_SIZE: usize = 1000;
-const BTREE_MAX_VAL_SIZE: usize = 3000;
+pub const HEADER: usize = 8;
+
+// describe 中每个 key 显示的字节数
+const DESCRIBE_KEY_PREFIX: usize = 16;
+
+// 默认配置
+pub const BTREE_PAGE_SIZE: usize = 4096;
+pub const BTREE_MAX_KEY_SIZE: usize = 1000;
+pub const BTREE_MAX_VAL_SIZE: usize = 3000;
+pub const BTREE_MAX_BLOB_SIZE: usize = 16 << 20;
+
+// 修改什么时候持久化
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Durability {
+    // 只在调用 commit 时提交，drop 时丢弃未提交的修改，可以用 is_dirty 检查
+    #[default]
+    Manual,
+    // 与 Manual 相同，drop 时提交未提交的修改
+    // drop 中无法返回错误，需要知道提交结果时应当在 drop 之前调用 commit
+    CommitOnDrop,
+    // 每次修改成功之后立即提交，崩溃时最多丢失正在进行的那一次修改
+    // Transaction 中的修改仍然在 Transaction 提交时一起提交
+    PerOp,
+}
+
+// page 大小以及 k-v 的长度限制，构造 BTree 时检查
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BTreeConfig {
+    pub page_size: usize,
+    pub max_key_size: usize,
+    // 超过 max_val_size 的 value 保存在 overflow page 中
+    pub max_val_size: usize,
+    pub max_blob_size: usize,
+    pub durability: Durability,
+}
+
+impl Default for BTreeConfig {
+    fn default() -> Self {
+        BTreeConfig {
+            page_size: BTREE_PAGE_SIZE,
+            max_key_size: BTREE_MAX_KEY_SIZE,
+            max_val_size: BTREE_MAX_VAL_SIZE,
+            max_blob_size: BTREE_MAX_BLOB_SIZE,
+            durability: Durability::Manual,
+        }
+    }
+}
+
+impl BTreeConfig {
+    // 给定 page 大小的默认配置，page 较小时缩小 k-v 的长度限制，
+    // 让一个最大的 k-v 仍然能放进一个 page
+    pub fn with_page_size(page_size: usize) -> Self {
+        let avail = page_size.saturating_sub(HEADER + 8 + 2 + 4);
+        let max_key_size = BTREE_MAX_KEY_SIZE.min(avail / 4);
+        BTreeConfig {
+            page_size,
+            max_key_size,
+            max_val_size: BTREE_MAX_VAL_SIZE.min(avail - max_key_size),
+            ..BTreeConfig::default()
+        }
+    }
+
+    // 一个 page 至少要能放下一个最大的 k-v，
+    // offset 是 u16，分裂之前的节点最多占用 2 个 page，
+    // 指向 overflow page 的引用保存在叶子节点中，不能超过 max_val_size
+    pub fn validate(&self) -> Result<(), BTreeError> {
+        let node1max = HEADER + 8 + 2 + 4 + self.max_key_size + self.max_val_size;
+        if node1max > self.page_size
+            || 2 * self.page_size > u16::MAX as usize
+            || self.max_val_size < OVERFLOW_REF_SIZE
+        {
+            return Err(BTreeError::InvalidConfig);
+        }
+        Ok(())
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct BNode {
-    data: Vec<u8>,
+    pub(crate) data: Vec<u8>,
 }
 
 impl BNode {
+    pub fn new(size: usize) -> Self {
+        BNode {
+            data: vec![0; size],
+        }
+    }
+
+    // 直接使用 page 的内容，不检查格式
+    pub fn from_bytes(data: Vec<u8>) -> Self {
+        BNode { data }
+    }
+
+    pub fn raw(&self) -> &[u8] {
+        &self.data
+    }
+
+    // 调试用的文本描述：类型、key 的数量、占用的字节数，以及每个 key 开头部分的十六进制
+    pub fn describe(&self) -> String {
+        let btype = match self.node_type() {
+            Ok(btype) => format!("{btype:?}"),
+            Err(_) => format!("Unknown({})", self.btype()),
+        };
+        let mut out = format!("type={btype} nkeys={}", self.nkeys());
+        // 无法识别或者已损坏的节点中 nkeys 和 offset 没有意义
+        let fits = HEADER + 10 * self.nkeys() as usize <= self.data.len()
+            && self.kv_pos(self.nkeys()) <= self.data.len();
+        if self.node_type().is_err() || !fits {
+            return out;
+        }
+        out.push_str(&format!(" bytes={}", self.n_bytes()));
+        for i in 0..self.nkeys() {
+            let key = self.get_key_ref(i);
+            let hex: String = key
+                .iter()
+                .take(DESCRIBE_KEY_PREFIX)
+                .map(|b| format!("{b:02x}"))
+                .collect();
+            let more = if key.len() > DESCRIBE_KEY_PREFIX {
+                ".."
+            } else {
+                ""
+            };
+            out.push_str(&format!("\n  [{i}] key={hex}{more} len={}", key.len()));
+            if let Ok(NodeType::Node) = self.node_type() {
+                out.push_str(&format!(" ptr={}", self.get_ptr(i)));
+            }
+        }
+        out
+    }
+
     // btyoe and nkeys
-    // | type | nkeys |  pointers  |   offsets  | key-values
-    // |  2B  |   2B  | nkeys * 8B | nkeys * 2B | ...
+    // | type | nkeys | checksum |  pointers  |   offsets  | key-values
+    // |  2B  |   2B  |    4B    | nkeys * 8B | nkeys * 2B | ...
+    // checksum 由 page store 在写入时计算，读取时检查
     pub fn btype(&self) -> u16 {
         u16::from_le_bytes(self.data[..2].try_into().unwrap())
     }
 
+    // 解析节点类型，类型无效时返回错误
+    pub fn node_type(&self) -> Result<NodeType, BTreeError> {
+        NodeType::try_from(self.btype())
+    }
+
     pub fn nkeys(&self) -> u16 {
         u16::from_le_bytes(self.data[2..4].try_into().unwrap())
     }
@@ -34,7 +171,7 @@ impl BNode {
         assert!(idx < self.nkeys());
 
         let pos = Self::ptr_pose(idx);
-        u64::from_le_bytes(self.data[pos..].try_into().unwrap())
+        u64::from_le_bytes(self.data[pos..pos + 8].try_into().unwrap())
     }
 
     pub fn set_ptr(&mut self, idx: u16, val: u64) {
@@ -61,7 +198,7 @@ impl BNode {
         }
 
         let pos = self.offset_pose(idx);
-        u16::from_le_bytes(self.data[pos..].try_into().unwrap())
+        u16::from_le_bytes(self.data[pos..pos + 2].try_into().unwrap())
     }
 
     pub fn set_offset(&mut self, idx: u16, offset: u16) {
@@ -72,6 +209,7 @@ impl BNode {
     // key-values
     // | klen | vlen | key | val |
     // |  2B  |  2B  | ... | ... |
+    // vlen 的最高位表示 val 是指向 overflow page 的引用
     pub fn kv_pos(&self, idx: u16) -> usize {
         assert!(idx <= self.nkeys());
 
@@ -82,50 +220,95 @@ impl BNode {
     }
 
     pub fn get_key(&self, idx: u16) -> Vec<u8> {
+        self.get_key_ref(idx).to_vec()
+    }
+
+    pub fn get_val(&self, idx: u16) -> Vec<u8> {
+        self.get_val_ref(idx).to_vec()
+    }
+
+    // 不复制数据，直接返回节点中的 key
+    pub fn get_key_ref(&self, idx: u16) -> &[u8] {
         assert!(idx < self.nkeys());
 
         let pos = self.kv_pos(idx);
-        let key_len = u16::from_le_bytes(self.data[pos..].try_into().unwrap());
+        let key_len = u16::from_le_bytes(self.data[pos..pos + 2].try_into().unwrap());
 
-        self.data[pos + 4..pos + 4 + key_len as usize].to_vec()
+        &self.data[pos + 4..pos + 4 + key_len as usize]
     }
 
-    pub fn get_val(&self, idx: u16) -> Vec<u8> {
+    // 不复制数据，直接返回节点中的 value
+    pub fn get_val_ref(&self, idx: u16) -> &[u8] {
         assert!(idx < self.nkeys());
 
         let pos = self.kv_pos(idx);
-        let key_len = u16::from_le_bytes(self.data[pos..].try_into().unwrap());
-        let val_len = u16::from_le_bytes(self.data[pos + 2..].try_into().unwrap());
+        let key_len = u16::from_le_bytes(self.data[pos..pos + 2].try_into().unwrap());
+        let val_len = u16::from_le_bytes(self.data[pos + 2..pos + 4].try_into().unwrap());
+        let val_len = val_len & !VAL_OVERFLOW;
 
         let base = pos + 4 + key_len as usize;
-        self.data[base..base + val_len as usize].to_vec()
+        &self.data[base..base + val_len as usize]
+    }
+
+    // value 是否保存在 overflow page 中
+    pub fn is_overflow(&self, idx: u16) -> bool {
+        assert!(idx < self.nkeys());
+
+        let pos = self.kv_pos(idx) + 2;
+        u16::from_le_bytes(self.data[pos..pos + 2].try_into().unwrap()) & VAL_OVERFLOW != 0
+    }
+
+    // 将 idx 处的 value 标记为 overflow 引用
+    pub fn set_overflow(&mut self, idx: u16) {
+        assert!(idx < self.nkeys());
+
+        let pos = self.kv_pos(idx) + 2;
+        let val_len = u16::from_le_bytes(self.data[pos..pos + 2].try_into().unwrap());
+        self.data[pos..pos + 2].copy_from_slice(&(val_len | VAL_OVERFLOW).to_le_bytes());
     }
 
     pub fn n_bytes(&self) -> u16 {
         self.kv_pos(self.nkeys()) as u16
     }
 
-    // 在节点中查找key
-    pub fn node_lookup_le(&self, key: &Vec<u8>) -> u16 {
-        let nkeys = self.nkeys();
-        let mut found = 0_u16;
+    // 在节点中查找key，返回最后一个 <= key 的位置，第一个 key 不参与比较
+    pub fn node_lookup_le(&self, key: &[u8]) -> u16 {
+        self.node_lookup_le_by(key, &ByteOrder)
+    }
 
-        for i in 1..nkeys {
-            let cmp = self.get_key(i).cmp(key);
-            if cmp != Ordering::Greater {
-                found = i;
+    // 按照 cmp 的顺序查找
+    pub fn node_lookup_le_by(&self, key: &[u8], cmp: &impl KeyComparator) -> u16 {
+        // key 是有序的，二分查找 [1, nkeys) 中第一个 > key 的位置
+        let (mut lo, mut hi) = (1_u16, self.nkeys().max(1));
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if cmp.compare(self.get_key_ref(mid), key).is_le() {
+                lo = mid + 1;
             } else {
-                break;
+                hi = mid;
             }
         }
 
-        found
+        lo - 1
     }
 
-    // 将key value 复制到当前节点
+    // 每个 key 都严格大于前一个 key，出现重复或者乱序的 key 时返回 CorruptPage
+    pub fn check_key_order(&self, cmp: &impl KeyComparator) -> Result<(), BTreeError> {
+        for i in 1..self.nkeys() {
+            if cmp
+                .compare(self.get_key_ref(i - 1), self.get_key_ref(i))
+                .is_ge()
+            {
+                return Err(BTreeError::CorruptPage);
+            }
+        }
+        Ok(())
+    }
+
+    // 将 old 中 [src_old, src_old + n) 的 key value 复制到当前节点的 [dst_new, dst_new + n)
     pub fn node_append_range(&mut self, old: &BNode, dst_new: u16, src_old: u16, n: u16) {
-        assert!(src_old + n < old.nkeys());
-        assert!(dst_new + n < self.nkeys());
+        assert!(src_old + n <= old.nkeys());
+        assert!(dst_new + n <= self.nkeys());
 
         if n == 0 {
             return;
@@ -133,13 +316,13 @@ impl BNode {
 
         // copy pointer
         for i in 0..n {
-            self.set_ptr(i, old.get_ptr(i));
+            self.set_ptr(dst_new + i, old.get_ptr(src_old + i));
         }
 
         // copy offset
         let dst_begin = self.get_offset(dst_new);
         let src_begin = old.get_offset(src_old);
-        for i in 1..n {
+        for i in 1..=n {
             let offset = dst_begin + old.get_offset(src_old + i) - src_begin;
             self.set_offset(dst_new + i, offset);
         }
@@ -147,177 +330,965 @@ impl BNode {
         // copy k-v
         let begin = old.kv_pos(src_old);
         let end = old.kv_pos(src_old + n);
-        self.data.copy_from_slice(&old.data[begin..end]);
+        let dst = self.kv_pos(dst_new);
+        self.data[dst..dst + end - begin].copy_from_slice(&old.data[begin..end]);
     }
 
     // 插入k-v
-    pub fn node_append_kv(&mut self, idx: u16, ptr: u64, key: Vec<u8>, val: Vec<u8>) {
+    // key 和 value 的长度必须能用 vlen 表示，value 的最高位是 overflow 标记；
+    // 累加之后的 offset 超出 u16 时返回 CorruptPage，超出 buffer 时返回 PageTooLarge
+    pub fn node_append_kv(
+        &mut self,
+        idx: u16,
+        ptr: u64,
+        key: Vec<u8>,
+        val: Vec<u8>,
+    ) -> Result<(), BTreeError> {
+        let klen = u16::try_from(key.len()).map_err(|_| BTreeError::KeyTooLong)?;
+        let vlen = u16::try_from(val.len())
+            .ok()
+            .filter(|vlen| vlen & VAL_OVERFLOW == 0)
+            .ok_or(BTreeError::ValueTooLong)?;
+        let offset = 4_u16
+            .checked_add(klen)
+            .and_then(|n| n.checked_add(vlen))
+            .and_then(|n| n.checked_add(self.get_offset(idx)))
+            .ok_or(BTreeError::CorruptPage)?;
+        let pos = self.kv_pos(idx);
+        if pos + 4 + key.len() + val.len() > self.data.len() {
+            return Err(BTreeError::PageTooLarge);
+        }
+
         // 插入子节点的指针
         self.set_ptr(idx, ptr);
 
         // 处理k-v
-        let pos = self.kv_pos(idx);
-        self.data[pos..pos + 2].copy_from_slice(&u16::to_le_bytes(key.len() as u16));
-        self.data[pos + 2..pos + 4].copy_from_slice(&u16::to_le_bytes(val.len() as u16));
+        self.data[pos..pos + 2].copy_from_slice(&klen.to_le_bytes());
+        self.data[pos + 2..pos + 4].copy_from_slice(&vlen.to_le_bytes());
         self.data[pos + 4..pos + 4 + key.len()].copy_from_slice(&key);
         self.data[pos + 4 + key.len()..pos + 4 + key.len() + val.len()].copy_from_slice(&val);
 
-        self.set_offset(
-            idx + 1,
-            self.get_offset(idx) + 4 + key.len() as u16 + val.len() as u16,
-        );
+        self.set_offset(idx + 1, offset);
+        Ok(())
     }
 
-    pub fn leaf_insert(&mut self, old: &BNode, idx: u16, key: Vec<u8>, val: Vec<u8>) {
+    pub fn leaf_insert(
+        &mut self,
+        old: &BNode,
+        idx: u16,
+        key: Vec<u8>,
+        val: Vec<u8>,
+    ) -> Result<(), BTreeError> {
         self.set_header(NodeType::Leaf as u16, old.nkeys() + 1);
         self.node_append_range(old, 0, 0, idx);
-        self.node_append_kv(idx, 0, key, val);
+        self.node_append_kv(idx, 0, key, val)?;
         self.node_append_range(old, idx + 1, idx, old.nkeys() - idx);
+        Ok(())
     }
 
-    pub fn leaf_update(&mut self, old: &BNode, idx: u16, key: Vec<u8>, val: Vec<u8>) {
+    pub fn leaf_update(
+        &mut self,
+        old: &BNode,
+        idx: u16,
+        key: Vec<u8>,
+        val: Vec<u8>,
+    ) -> Result<(), BTreeError> {
         self.set_header(NodeType::Leaf as u16, old.nkeys());
-        if idx > 0 {
-            self.node_append_range(old, 0, 0, idx - 1);
-        }
-        self.node_append_kv(idx, 0, key, val);
-        self.node_append_range(old, idx + 1, idx + 1, old.nkeys() - idx);
+        self.node_append_range(old, 0, 0, idx);
+        self.node_append_kv(idx, 0, key, val)?;
+        self.node_append_range(old, idx + 1, idx + 1, old.nkeys() - (idx + 1));
+        Ok(())
+    }
+
+    pub fn leaf_delete(&mut self, old: &BNode, idx: u16) {
+        self.set_header(NodeType::Leaf as u16, old.nkeys() - 1);
+        self.node_append_range(old, 0, 0, idx);
+        self.node_append_range(old, idx, idx + 1, old.nkeys() - (idx + 1));
+    }
+
+    // 合并两个相邻的节点
+    pub fn node_merge(&mut self, left: &BNode, right: &BNode) {
+        self.set_header(left.btype(), left.nkeys() + right.nkeys());
+        self.node_append_range(left, 0, 0, left.nkeys());
+        self.node_append_range(right, left.nkeys(), 0, right.nkeys());
+    }
+
+    // 用合并后的节点替换 old 中 idx 和 idx + 1 两个子节点
+    pub fn node_replace_2kid(
+        &mut self,
+        old: &BNode,
+        idx: u16,
+        ptr: u64,
+        key: Vec<u8>,
+    ) -> Result<(), BTreeError> {
+        self.set_header(NodeType::Node as u16, old.nkeys() - 1);
+        self.node_append_range(old, 0, 0, idx);
+        self.node_append_kv(idx, ptr, key, vec![])?;
+        self.node_append_range(old, idx + 1, idx + 2, old.nkeys() - (idx + 2));
+        Ok(())
     }
 
-    // 分割节点
-    pub fn node_split_3(&mut self) -> (u16, Vec<BNode>) {
-        if self.n_bytes() as usize <= BTREE_PAGE_SIZE {
-            // self.data = self.data[..BTREE_PAGE_SIZE];
-            return (1, vec![self.clone()]);
+    // 分割节点，每个结果节点都不超过 page_size
+    pub fn node_split_3(&mut self, page_size: usize) -> (u16, Vec<BNode>) {
+        self.node_split_3_in(page_size, &mut NodeArena::new(0))
+    }
+
+    // 与 node_split_3 相同，临时节点从 arena 中取出
+    // 不需要分裂时直接移走 self 的 buffer，调用之后 self 为空
+    pub(crate) fn node_split_3_in(
+        &mut self,
+        page_size: usize,
+        arena: &mut NodeArena,
+    ) -> (u16, Vec<BNode>) {
+        if self.n_bytes() as usize <= page_size {
+            return (1, vec![std::mem::replace(self, BNode { data: vec![] })]);
         }
 
-        let mut left = BNode {
-            data: vec![0; 2 * BTREE_PAGE_SIZE],
-        };
-        let mut right = BNode {
-            data: vec![0; BTREE_PAGE_SIZE],
-        };
+        let mut left = arena.take(2 * page_size);
+        let mut right = arena.take(page_size);
 
-        self.node_split_2(&mut left, &mut right);
-        if left.n_bytes() as usize <= BTREE_PAGE_SIZE {
-            left.data = left.data[..BTREE_PAGE_SIZE].to_vec();
+        self.node_split_2(&mut left, &mut right, page_size);
+        if left.n_bytes() as usize <= page_size {
+            left.data.truncate(page_size);
             return (2, vec![left, right]);
         }
 
-        let mut left_left = BNode {
-            data: vec![0; BTREE_PAGE_SIZE],
-        };
-        let mut middle = BNode {
-            data: vec![0; BTREE_PAGE_SIZE],
-        };
-        self.node_split_2(&mut left_left, &mut middle);
-        assert!(left_left.n_bytes() as usize <= BTREE_PAGE_SIZE);
+        let mut left_left = arena.take(page_size);
+        let mut middle = arena.take(page_size);
+        left.node_split_2(&mut left_left, &mut middle, page_size);
+        assert!(left_left.n_bytes() as usize <= page_size);
+        arena.put(left);
 
-        return (3, vec![left_left, middle, right]);
+        (3, vec![left_left, middle, right])
     }
 
-    pub fn node_split_2(&self, left: &mut BNode, right: &mut BNode) {
-        todo!()
+    // 将节点分为两部分，right 保存尾部的 key 并且一定能放进一个 page，left 保存剩余的 key
+    // 从中间开始分，两边大小接近，顺序插入时不会让 left 只剩下一个 key
+    pub fn node_split_2(&self, left: &mut BNode, right: &mut BNode, page_size: usize) {
+        let nkeys = self.nkeys();
+        assert!(nkeys >= 2);
+
+        let left_bytes = |n: u16| HEADER + 10 * n as usize + self.get_offset(n) as usize;
+        let right_bytes = |n: u16| {
+            HEADER
+                + 10 * (nkeys - n) as usize
+                + (self.get_offset(nkeys) - self.get_offset(n)) as usize
+        };
+
+        // 先让 left 装进一个 page，再保证 right 装得下，left 和 right 至少各有一个 key
+        let mut nleft = nkeys / 2;
+        while nleft > 1 && left_bytes(nleft) > page_size {
+            nleft -= 1;
+        }
+        while nleft < nkeys - 1 && right_bytes(nleft) > page_size {
+            nleft += 1;
+        }
+        assert!(right_bytes(nleft) <= page_size);
+        let nright = nkeys - nleft;
+
+        left.set_header(self.btype(), nleft);
+        left.node_append_range(self, 0, 0, nleft);
+
+        right.set_header(self.btype(), nright);
+        right.node_append_range(self, 0, nleft, nright);
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u16)]
 pub enum NodeType {
     Node = 1,
     Leaf = 2,
 }
 
-impl From<u16> for NodeType {
-    fn from(value: u16) -> Self {
+impl TryFrom<u16> for NodeType {
+    type Error = BTreeError;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
         match value {
-            1 => NodeType::Node,
-            2 => NodeType::Leaf,
-            _ => panic!("Invalid value"),
+            1 => Ok(NodeType::Node),
+            2 => Ok(NodeType::Leaf),
+            _ => Err(BTreeError::InvalidNodeType(value)),
         }
     }
 }
 
-#[derive(Debug)]
-pub struct BTree {
+pub type KeyValue = (Vec<u8>, Vec<u8>);
+
+// 插入时叶子节点中 key 的新 value
+pub(crate) enum Update<'a> {
+    Value(&'a [u8]),
+    // key 存在时为 merge(旧的 value)，否则为 init
+    Merge {
+        init: &'a [u8],
+        merge: &'a dyn Fn(&[u8]) -> Vec<u8>,
+    },
+    // 不经过 codec 和 overflow，原样写入叶子节点，用于哨兵中的索引目录
+    Raw(&'a [u8]),
+}
+
+impl Update<'_> {
+    fn resolve(&self, old: Option<&[u8]>) -> Vec<u8> {
+        match (self, old) {
+            (Update::Value(val), _) | (Update::Raw(val), _) => val.to_vec(),
+            (Update::Merge { merge, .. }, Some(old)) => merge(old),
+            (Update::Merge { init, .. }, None) => init.to_vec(),
+        }
+    }
+}
+
+// key 的顺序由 C 决定，默认按字节比较
+pub struct BTree<C: KeyComparator = ByteOrder> {
     root: u64,
+    // 最近一次提交或者打开时的根节点，与 root 不同时有未提交的修改
+    committed: u64,
+    store: Box<dyn PageStore>,
+    config: BTreeConfig,
+    // 每个存活的 Snapshot 持有一个引用
+    pub(crate) snapshots: Arc<()>,
+    // 存在 Snapshot 时释放的 page，所有 Snapshot 都结束之后才真正释放
+    deferred: Vec<u64>,
+    // 插入过程中要释放的 page，新的根节点分配成功之后才释放，出错时丢弃
+    pending_free: Option<Vec<u64>>,
+    // 每次分配或释放 page 时加一
+    pub(crate) generation: u64,
+    // 存在 Snapshot 期间每个 page 最近一次分配或释放时的 generation，没有记录的 page 视为 0
+    // page 被释放或重新分配之后 generation 变大，旧的读者可以发现
+    // 没有 Snapshot 时不需要记录，已有的记录全部清除
+    pub(crate) generations: HashMap<u64, u64>,
+    // 进行中的 Transaction
+    pub(crate) txn: Option<TxnState>,
+    cmp: C,
+    // 设置之后 value 在写入前压缩
+    codec: Option<Box<dyn ValueCodec>>,
+    // 插入和删除时临时节点的 buffer 池
+    pub(crate) arena: NodeArena,
 }
 
 impl BTree {
-    pub fn new(&self, node: &BNode) -> u64 {
-        todo!()
+    // 使用与 store 的 page 大小对应的默认配置，从 store 中最近一次提交的根节点打开树
+    // page 太小或太大，无法构成有效的配置时 panic
+    pub fn with_store(store: Box<dyn PageStore>) -> Self {
+        let config = BTreeConfig::with_page_size(store.page_size());
+        Self::with_config(store, config).unwrap()
     }
 
-    pub fn get(&self, ptr: u64) -> BNode {
-        todo!()
+    // config 无效或者与 store 的 page 大小不一致时返回 InvalidConfig
+    pub fn with_config(store: Box<dyn PageStore>, config: BTreeConfig) -> Result<Self, BTreeError> {
+        Self::with_comparator(store, config, ByteOrder)
     }
+}
+
+impl<C: KeyComparator> BTree<C> {
+    // 使用自定义的 key 顺序，打开已有的树时必须与创建时的 cmp 一致
+    pub fn with_comparator(
+        store: Box<dyn PageStore>,
+        config: BTreeConfig,
+        cmp: C,
+    ) -> Result<Self, BTreeError> {
+        config.validate()?;
+        if store.page_size() != config.page_size {
+            return Err(BTreeError::InvalidConfig);
+        }
 
-    pub fn del(&self, root: u64) {
-        todo!()
+        Ok(BTree {
+            root: store.root(),
+            committed: store.root(),
+            store,
+            config,
+            snapshots: Arc::new(()),
+            deferred: vec![],
+            pending_free: None,
+            generation: 0,
+            generations: HashMap::new(),
+            txn: None,
+            cmp,
+            codec: None,
+            arena: NodeArena::default(),
+        })
     }
 
-    // 向node中插入k-v，有可能会导致节点分裂
-    pub fn tree_insert(&self, node: &BNode, key: Vec<u8>, val: Vec<u8>) -> BNode {
-        let mut new_node = BNode {
-            data: vec![0; 2 * BTREE_PAGE_SIZE],
+    // 压缩之后写入 value，打开已有的树时必须与创建时的 codec 一致
+    pub fn with_codec(mut self, codec: Box<dyn ValueCodec>) -> Self {
+        self.codec = Some(codec);
+        self
+    }
+
+    pub fn root_ptr(&self) -> u64 {
+        self.root
+    }
+
+    pub(crate) fn set_root(&mut self, root: u64) {
+        self.root = root;
+    }
+
+    pub fn config(&self) -> &BTreeConfig {
+        &self.config
+    }
+
+    pub fn comparator(&self) -> &C {
+        &self.cmp
+    }
+
+    // store 的 page 操作次数
+    pub fn store_stats(&self) -> StoreStats {
+        self.store.stats()
+    }
+
+    pub fn reset_store_stats(&self) {
+        self.store.reset_stats()
+    }
+
+    // 分配一个新的 page，返回 page 指针
+    #[allow(clippy::new_ret_no_self, clippy::wrong_self_convention)]
+    pub fn new(&mut self, node: &BNode) -> Result<u64, BTreeError> {
+        let ptr = self.store.alloc(node)?;
+        self.bump_generation(ptr);
+        if let Some(txn) = &mut self.txn {
+            txn.allocated.insert(ptr);
+        }
+        Ok(ptr)
+    }
+
+    // 读取 page 并检查节点类型
+    pub fn get(&self, ptr: u64) -> Result<BNode, BTreeError> {
+        let node = self.store.get(ptr)?;
+        node.node_type()?;
+        Ok(node)
+    }
+
+    // 读取 generation 时刻的树中的 page，之后被释放过时返回 StalePage
+    pub(crate) fn get_at(&self, ptr: u64, generation: u64) -> Result<BNode, BTreeError> {
+        self.check_generation(ptr, generation)?;
+        self.get(ptr)
+    }
+
+    // 读取 page，不检查节点类型
+    pub(crate) fn get_page(&self, ptr: u64) -> Result<BNode, BTreeError> {
+        self.store.get(ptr)
+    }
+
+    // copy-on-write 时子节点总是先于父节点分配，
+    // 所以 generation 时刻可以访问的 page 都不会比它新
+    pub(crate) fn check_generation(&self, ptr: u64, generation: u64) -> Result<(), BTreeError> {
+        match self.generations.get(&ptr) {
+            Some(&allocated) if allocated > generation => Err(BTreeError::StalePage),
+            _ => Ok(()),
+        }
+    }
+
+    // 释放 page，存在 Snapshot 时推迟到所有 Snapshot 结束之后
+    // Transaction 中释放的旧 page 推迟到提交时，以便回滚
+    pub fn del(&mut self, ptr: u64) {
+        if let Some(pending) = &mut self.pending_free {
+            pending.push(ptr);
+            return;
+        }
+        if let Some(txn) = &mut self.txn {
+            if !txn.allocated.remove(&ptr) {
+                txn.freed.push(ptr);
+                return;
+            }
+        }
+        if Arc::strong_count(&self.snapshots) > 1 {
+            self.deferred.push(ptr);
+            return;
+        }
+        self.release_deferred();
+        self.free_page(ptr)
+    }
+
+    fn release_deferred(&mut self) {
+        if Arc::strong_count(&self.snapshots) > 1 {
+            return;
+        }
+        for ptr in std::mem::take(&mut self.deferred) {
+            self.free_page(ptr);
+        }
+    }
+
+    // 每个 Snapshot 都持有 snapshots 的 Weak 引用，不论是否固定 page
+    fn bump_generation(&mut self, ptr: u64) {
+        self.generation += 1;
+        if Arc::weak_count(&self.snapshots) > 0 {
+            self.generations.insert(ptr, self.generation);
+        } else if !self.generations.is_empty() {
+            self.generations.clear();
+        }
+    }
+
+    fn free_page(&mut self, ptr: u64) {
+        self.bump_generation(ptr);
+        self.store.free(ptr);
+    }
+
+    // 持久化当前的树
+    // 仍被 Snapshot 引用的 page 不会进入 free list，直到之后的某次提交
+    pub fn commit(&mut self) -> Result<(), BTreeError> {
+        self.release_deferred();
+        self.store.commit(self.root)?;
+        self.committed = self.root;
+        Ok(())
+    }
+
+    // 是否有未提交的修改
+    pub fn is_dirty(&self) -> bool {
+        self.root != self.committed
+    }
+
+    // Durability::PerOp 时在每个公开的修改操作成功之后调用
+    // Transaction 进行中或者临时切换到索引树时不提交
+    pub(crate) fn auto_commit(&mut self) -> Result<(), BTreeError> {
+        if self.config.durability == Durability::PerOp && self.txn.is_none() {
+            return self.commit();
+        }
+        Ok(())
+    }
+
+    // 插入或更新 k-v，根节点分裂时树的高度加一
+    // value 的大小限制作用于压缩之后的大小
+    pub fn insert(&mut self, key: &[u8], val: &[u8]) -> Result<(), BTreeError> {
+        self.insert_value(key, val)?;
+        self.auto_commit()
+    }
+
+    // 与 insert 相同，但不会自动提交
+    pub(crate) fn insert_value(&mut self, key: &[u8], val: &[u8]) -> Result<(), BTreeError> {
+        if self.codec.is_none() && val.len() > self.config.max_blob_size {
+            return Err(BTreeError::ValueTooLong);
+        }
+        self.insert_with(key, Update::Value(val))
+    }
+
+    // key 存在时用 merge(旧的 value) 的结果更新，否则插入 init，只需要一次查找
+    pub fn upsert(
+        &mut self,
+        key: &[u8],
+        init: &[u8],
+        merge: impl Fn(&[u8]) -> Vec<u8>,
+    ) -> Result<(), BTreeError> {
+        self.insert_with(
+            key,
+            Update::Merge {
+                init,
+                merge: &merge,
+            },
+        )?;
+        self.auto_commit()
+    }
+
+    // 按顺序把 other 中的 k-v 合并进来，key 已经存在时写入 on_conflict(已有的 value, other 的 value)
+    // 每个 key 只查找一次，按顺序写入时相邻的 key 修改的是同一条路径，全部合并之后才自动提交
+    pub fn merge_from(
+        &mut self,
+        other: &BTree<C>,
+        on_conflict: impl Fn(&[u8], &[u8]) -> Vec<u8>,
+    ) -> Result<(), BTreeError> {
+        let mut iter = other.iter();
+        for (key, val) in iter.by_ref() {
+            let merge = |existing: &[u8]| on_conflict(existing, &val);
+            self.insert_with(
+                &key,
+                Update::Merge {
+                    init: &val,
+                    merge: &merge,
+                },
+            )?;
+        }
+        match iter.error() {
+            Some(err) => Err(err.clone()),
+            None => self.auto_commit(),
+        }
+    }
+
+    fn insert_with(&mut self, key: &[u8], update: Update) -> Result<(), BTreeError> {
+        if key.is_empty() {
+            return Err(BTreeError::EmptyKey);
+        }
+        if key.len() > self.config.max_key_size {
+            return Err(BTreeError::KeyTooLong);
+        }
+        self.insert_update(key, update)
+    }
+
+    // 不检查 key，空 key 只用于更新哨兵
+    pub(crate) fn insert_update(&mut self, key: &[u8], update: Update) -> Result<(), BTreeError> {
+        // 新的根节点以空 key 开头，之后插入的 key 都比它大，
+        // 所以 node_lookup_le 总能找到一个 <= key 的位置
+        if self.root == 0 {
+            let (val, overflow) = self.encode_val(update.resolve(None))?;
+            let mut root = BNode::new(self.config.page_size);
+            root.set_header(NodeType::Leaf as u16, 2);
+            root.node_append_kv(0, 0, vec![], vec![])?;
+            root.node_append_kv(1, 0, key.to_vec(), val)?;
+            if overflow {
+                root.set_overflow(1);
+            }
+            self.root = self.new(&root)?;
+            return Ok(());
+        }
+
+        // 旧的 page 在新的根节点分配之后才释放，出错时旧的树仍然完整
+        self.pending_free = Some(vec![]);
+        let result = self.insert_root(key, &update);
+        let pending = self.pending_free.take().unwrap();
+        result?;
+        for ptr in pending {
+            self.del(ptr);
+        }
+        Ok(())
+    }
+
+    fn insert_root(&mut self, key: &[u8], update: &Update) -> Result<(), BTreeError> {
+        let node = self.get(self.root)?;
+        let mut node = self.tree_insert(&node, key.to_vec(), update)?;
+        self.del(self.root);
+
+        let (n, split) = node.node_split_3_in(self.config.page_size, &mut self.arena);
+        self.arena.put(node);
+        if n > 1 {
+            let mut root = self.arena.take(self.config.page_size);
+            root.set_header(NodeType::Node as u16, n);
+            for (i, kid) in split.into_iter().enumerate() {
+                let ptr = self.new(&kid)?;
+                root.node_append_kv(i as u16, ptr, kid.get_key(0), vec![])?;
+                self.arena.put(kid);
+            }
+            self.root = self.new(&root)?;
+            self.arena.put(root);
+        } else {
+            let [root] = <[BNode; 1]>::try_from(split).unwrap();
+            self.root = self.new(&root)?;
+            self.arena.put(root);
+        }
+
+        Ok(())
+    }
+
+    // 较大的 value 写入 overflow page，叶子节点中只保存引用
+    pub(crate) fn encode_val(&mut self, val: Vec<u8>) -> Result<(Vec<u8>, bool), BTreeError> {
+        let val = match &self.codec {
+            Some(codec) => {
+                let compressed = codec.compress(&val);
+                let (flag, data) = if compressed.len() < val.len() {
+                    (VAL_COMPRESSED, compressed)
+                } else {
+                    (VAL_RAW, val)
+                };
+                let mut stored = Vec::with_capacity(1 + data.len());
+                stored.push(flag);
+                stored.extend_from_slice(&data);
+                stored
+            }
+            None => val,
         };
+        if val.len() > self.config.max_blob_size {
+            return Err(BTreeError::ValueTooLong);
+        }
+        if val.len() > self.config.max_val_size {
+            return Ok((self.write_overflow(&val)?, true));
+        }
+        Ok((val, false))
+    }
+
+    // 读取叶子节点中 idx 处完整的 value，设置了 codec 时解压
+    pub(crate) fn leaf_val(&self, leaf: &BNode, idx: u16) -> Result<Vec<u8>, BTreeError> {
+        self.leaf_val_at(leaf, idx, self.generation)
+    }
 
-        let idx = node.node_lookup_le(&key);
-        match NodeType::try_from(node.btype()) {
-            Ok(node_type) => match node_type {
+    // overflow page 同样按 generation 检查
+    pub(crate) fn leaf_val_at(
+        &self,
+        leaf: &BNode,
+        idx: u16,
+        generation: u64,
+    ) -> Result<Vec<u8>, BTreeError> {
+        let stored = if leaf.is_overflow(idx) {
+            self.read_overflow(leaf.get_val_ref(idx), generation)?
+        } else {
+            leaf.get_val(idx)
+        };
+        let Some(codec) = &self.codec else {
+            return Ok(stored);
+        };
+        match stored.split_first() {
+            Some((&VAL_RAW, data)) => Ok(data.to_vec()),
+            Some((&VAL_COMPRESSED, data)) => codec.decompress(data),
+            _ => Err(BTreeError::CorruptPage),
+        }
+    }
+
+    // 叶子节点中的 value 是否就是原来的 value，不需要读取 overflow page 或者解压
+    pub(crate) fn is_plain(&self, leaf: &BNode, idx: u16) -> bool {
+        self.codec.is_none() && !leaf.is_overflow(idx)
+    }
+
+    // key 不存在时返回 None，value 为空时返回 Some(vec![])
+    pub fn get_value(&self, key: &[u8]) -> Result<Option<Vec<u8>>, BTreeError> {
+        self.get_value_at(self.root, self.generation, key)
+    }
+
+    // 在 generation 时刻以 root 为根的树中查找 key
+    pub(crate) fn get_value_at(
+        &self,
+        root: u64,
+        generation: u64,
+        key: &[u8],
+    ) -> Result<Option<Vec<u8>>, BTreeError> {
+        match self.find_leaf(root, generation, key)? {
+            Some((leaf, idx)) => self.leaf_val_at(&leaf, idx, generation).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    // 最小的 k-v
+    pub fn min(&self) -> Result<Option<KeyValue>, BTreeError> {
+        self.edge(false)
+    }
+
+    // 最大的 k-v
+    pub fn max(&self) -> Result<Option<KeyValue>, BTreeError> {
+        self.edge(true)
+    }
+
+    // 沿着第一个或最后一个指针下降到叶子节点，由 ScanIter 跳过哨兵
+    fn edge(&self, reverse: bool) -> Result<Option<KeyValue>, BTreeError> {
+        let mut iter = ScanIter::new(self, self.root, Bound::Unbounded, Bound::Unbounded, reverse);
+        let item = iter.next();
+        match iter.error() {
+            Some(err) => Err(err.clone()),
+            None => Ok(item),
+        }
+    }
+
+    // 只比较 key，不读取 value
+    pub fn contains_key(&self, key: &[u8]) -> Result<bool, BTreeError> {
+        Ok(self.find_leaf(self.root, self.generation, key)?.is_some())
+    }
+
+    // 返回包含 key 的叶子节点以及 key 的位置
+    fn find_leaf(
+        &self,
+        root: u64,
+        generation: u64,
+        key: &[u8],
+    ) -> Result<Option<(BNode, u16)>, BTreeError> {
+        if root == 0 || key.is_empty() {
+            return Ok(None);
+        }
+
+        let mut node = self.get_at(root, generation)?;
+        loop {
+            let idx = node.node_lookup_le_by(key, &self.cmp);
+            match node.node_type()? {
                 NodeType::Leaf => {
-                    if key.eq(&node.get_key(idx)) {
-                        new_node.leaf_update(node, idx, key, val);
-                    } else {
-                        new_node.leaf_insert(node, idx + 1, key, val);
+                    if self.cmp.compare(node.get_key_ref(idx), key).is_ne() {
+                        return Ok(None);
                     }
+                    return Ok(Some((node, idx)));
                 }
-                NodeType::Node => {
-                    self.node_insert(&new_node, node, idx, key, val);
+                NodeType::Node => node = self.get_at(node.get_ptr(idx), generation)?,
+            }
+        }
+    }
+
+    // 删除 key，返回 key 是否存在
+    pub fn delete(&mut self, key: &[u8]) -> Result<bool, BTreeError> {
+        let found = self.delete_key(key)?;
+        self.auto_commit()?;
+        Ok(found)
+    }
+
+    // 与 delete 相同，但不会自动提交
+    pub(crate) fn delete_key(&mut self, key: &[u8]) -> Result<bool, BTreeError> {
+        // 空 key 是哨兵，不能删除
+        if self.root == 0 || key.is_empty() {
+            return Ok(false);
+        }
+
+        let node = self.get(self.root)?;
+        let Some(updated) = self.tree_delete(&node, key)? else {
+            return Ok(false);
+        };
+
+        self.del(self.root);
+        self.root = match updated.node_type()? {
+            NodeType::Node if updated.nkeys() == 1 => self.collapse_root(updated.get_ptr(0))?,
+            NodeType::Leaf if updated.nkeys() == 0 || only_sentinel(&updated) => 0,
+            _ => self.new(&updated)?,
+        };
+        self.arena.put(updated);
+
+        Ok(true)
+    }
+
+    // 释放所有 page，包括二级索引，回到空树
+    pub fn clear(&mut self) -> Result<(), BTreeError> {
+        if self.root != 0 {
+            for (_, root) in self.catalog()? {
+                self.free_subtree(root)?;
+            }
+            self.free_subtree(self.root)?;
+            self.root = 0;
+        }
+        self.auto_commit()
+    }
+
+    // 后序遍历释放子树，包括叶子节点引用的 overflow page
+    fn free_subtree(&mut self, ptr: u64) -> Result<(), BTreeError> {
+        let node = self.get(ptr)?;
+        for i in 0..node.nkeys() {
+            match node.node_type()? {
+                NodeType::Leaf if node.is_overflow(i) => self.free_overflow(node.get_val_ref(i))?,
+                NodeType::Leaf => {}
+                NodeType::Node => self.free_subtree(node.get_ptr(i))?,
+            }
+        }
+        self.del(ptr);
+        Ok(())
+    }
+
+    // 只有一个子节点的内部根节点被这个子节点代替，树的高度减一，
+    // 直到根节点有多个子节点或者是叶子节点，返回新的根节点
+    // 叶子节点中只剩下哨兵时回到空树
+    fn collapse_root(&mut self, mut ptr: u64) -> Result<u64, BTreeError> {
+        loop {
+            let node = self.get(ptr)?;
+            match node.node_type()? {
+                NodeType::Node if node.nkeys() == 1 => {
+                    self.del(ptr);
+                    ptr = node.get_ptr(0);
                 }
-            },
-            Err(_) => panic!("node error"),
+                NodeType::Leaf if only_sentinel(&node) => {
+                    self.del(ptr);
+                    return Ok(0);
+                }
+                _ => return Ok(ptr),
+            }
+        }
+    }
+
+    // 向node中插入k-v，有可能会导致节点分裂
+    pub(crate) fn tree_insert(
+        &mut self,
+        node: &BNode,
+        key: Vec<u8>,
+        update: &Update,
+    ) -> Result<BNode, BTreeError> {
+        let mut new_node = self.arena.take(2 * self.config.page_size);
+
+        let idx = node.node_lookup_le_by(&key, &self.cmp);
+        match node.node_type()? {
+            NodeType::Leaf => {
+                // 只有 merge 需要读取旧的 value
+                let found = self.cmp.compare(node.get_key_ref(idx), &key);
+                let old = match (found, update) {
+                    (Ordering::Equal, Update::Merge { .. }) => Some(self.leaf_val(node, idx)?),
+                    _ => None,
+                };
+                let (val, overflow) = match update {
+                    Update::Raw(val) => (val.to_vec(), false),
+                    _ => self.encode_val(update.resolve(old.as_deref()))?,
+                };
+
+                let pos = match found {
+                    Ordering::Equal => {
+                        // 被覆盖的 value 所占用的 overflow page 不再需要
+                        if node.is_overflow(idx) {
+                            self.free_overflow(node.get_val_ref(idx))?;
+                        }
+                        new_node.leaf_update(node, idx, key, val)?;
+                        idx
+                    }
+                    // node_lookup_le 不比较第一个 key，比所有 key 都小时插入到最前面
+                    Ordering::Greater => {
+                        new_node.leaf_insert(node, idx, key, val)?;
+                        idx
+                    }
+                    Ordering::Less => {
+                        new_node.leaf_insert(node, idx + 1, key, val)?;
+                        idx + 1
+                    }
+                };
+                if overflow {
+                    new_node.set_overflow(pos);
+                }
+                // 插入位置算错时会出现重复的 key，debug 构建中检查整个叶子节点
+                if cfg!(debug_assertions) {
+                    new_node.check_key_order(&self.cmp)?;
+                }
+            }
+            NodeType::Node => {
+                self.node_insert(&mut new_node, node, idx, key, update)?;
+            }
         };
 
-        new_node
+        Ok(new_node)
     }
 
     // 更新内部节点
     pub fn node_replace_kid_n(
-        &self,
+        &mut self,
         new_node: &mut BNode,
         old: &BNode,
         idx: u16,
         kids: Vec<BNode>,
-    ) {
+    ) -> Result<(), BTreeError> {
         let inc = kids.len() as u16;
         new_node.set_header(NodeType::Node as u16, old.nkeys() + inc - 1);
         new_node.node_append_range(old, 0, 0, idx);
-        for (i, node) in kids.iter().enumerate() {
-            new_node.node_append_kv(idx + i as u16, self.new(node), node.get_key(0), vec![]);
+        for (i, node) in kids.into_iter().enumerate() {
+            let ptr = self.new(&node)?;
+            new_node.node_append_kv(idx + i as u16, ptr, node.get_key(0), vec![])?;
+            self.arena.put(node);
         }
 
         new_node.node_append_range(old, idx + inc, idx + 1, old.nkeys() - (idx + 1));
+        Ok(())
     }
 
     // 处理node节点
-    pub fn node_insert(
-        &self,
-        new_node: &BNode,
+    pub(crate) fn node_insert(
+        &mut self,
+        new_node: &mut BNode,
         node: &BNode,
         idx: u16,
         key: Vec<u8>,
-        val: Vec<u8>,
-    ) {
+        update: &Update,
+    ) -> Result<(), BTreeError> {
         let kid_ptr = node.get_ptr(idx);
-        let kid_node = self.get(kid_ptr);
+        let kid_node = self.get(kid_ptr)?;
+
+        let mut kid_node = self.tree_insert(&kid_node, key, update)?;
+        self.del(kid_ptr);
+        let (_, split) = kid_node.node_split_3_in(self.config.page_size, &mut self.arena);
+        self.arena.put(kid_node);
+        self.node_replace_kid_n(new_node, node, idx, split)
+    }
+
+    // 从 node 中删除 key，key 不存在时返回 None
+    pub fn tree_delete(&mut self, node: &BNode, key: &[u8]) -> Result<Option<BNode>, BTreeError> {
+        let idx = node.node_lookup_le_by(key, &self.cmp);
+        match node.node_type()? {
+            NodeType::Leaf => {
+                if self.cmp.compare(node.get_key_ref(idx), key).is_ne() {
+                    return Ok(None);
+                }
+                if node.is_overflow(idx) {
+                    self.free_overflow(node.get_val_ref(idx))?;
+                }
+
+                let mut new_node = self.arena.take(self.config.page_size);
+                new_node.leaf_delete(node, idx);
+                Ok(Some(new_node))
+            }
+            NodeType::Node => self.node_delete(node, idx, key),
+        }
+    }
 
+    // 处理 node 节点的删除，子节点过小时与兄弟节点合并
+    fn node_delete(
+        &mut self,
+        node: &BNode,
+        idx: u16,
+        key: &[u8],
+    ) -> Result<Option<BNode>, BTreeError> {
+        let kid_ptr = node.get_ptr(idx);
+        let kid_node = self.get(kid_ptr)?;
+        let Some(updated) = self.tree_delete(&kid_node, key)? else {
+            return Ok(None);
+        };
         self.del(kid_ptr);
-        let kid_node = self.tree_insert(&kid_node, key, val);
+
+        let mut new_node = self.arena.take(self.config.page_size);
+        match self.should_merge(node, idx, &updated)? {
+            Some((Ordering::Less, sibling)) => {
+                let mut merged = self.arena.take(self.config.page_size);
+                merged.node_merge(&sibling, &updated);
+                self.del(node.get_ptr(idx - 1));
+                let ptr = self.new(&merged)?;
+                new_node.node_replace_2kid(node, idx - 1, ptr, merged.get_key(0))?;
+                self.arena.put(merged);
+                self.arena.put(updated);
+            }
+            Some((_, sibling)) => {
+                let mut merged = self.arena.take(self.config.page_size);
+                merged.node_merge(&updated, &sibling);
+                self.del(node.get_ptr(idx + 1));
+                let ptr = self.new(&merged)?;
+                new_node.node_replace_2kid(node, idx, ptr, merged.get_key(0))?;
+                self.arena.put(merged);
+                self.arena.put(updated);
+            }
+            None => {
+                // 没有可以合并的兄弟节点，子节点为空时将其从 node 中移除
+                let kids = if updated.nkeys() == 0 {
+                    self.arena.put(updated);
+                    vec![]
+                } else {
+                    vec![updated]
+                };
+                self.node_replace_kid_n(&mut new_node, node, idx, kids)?;
+            }
+        }
+
+        Ok(Some(new_node))
+    }
+
+    // 子节点小于 1/4 page 时，尝试与左(Less)或右(Greater)兄弟节点合并
+    pub fn should_merge(
+        &self,
+        node: &BNode,
+        idx: u16,
+        updated: &BNode,
+    ) -> Result<Option<(Ordering, BNode)>, BTreeError> {
+        let page_size = self.config.page_size;
+        if updated.n_bytes() as usize > page_size / 4 {
+            return Ok(None);
+        }
+
+        if idx > 0 {
+            let sibling = self.get(node.get_ptr(idx - 1))?;
+            let merged = sibling.n_bytes() as usize + updated.n_bytes() as usize - HEADER;
+            if merged <= page_size {
+                return Ok(Some((Ordering::Less, sibling)));
+            }
+        }
+
+        if idx + 1 < node.nkeys() {
+            let sibling = self.get(node.get_ptr(idx + 1))?;
+            let merged = sibling.n_bytes() as usize + updated.n_bytes() as usize - HEADER;
+            if merged <= page_size {
+                return Ok(Some((Ordering::Greater, sibling)));
+            }
+        }
+
+        Ok(None)
     }
 }
 
-fn init() {
-    let node1max = HEADER + 8 + 2 + 4 + BTREE_MAX_KEY_SIZE + BTREE_MAX_VAL_SIZE;
-    assert!(node1max <= BTREE_PAGE_SIZE)
+// 每个 k-v 的固定开销: pointer(8B) + offset(2B) + klen(2B) + vlen(2B)
+const KV_OVERHEAD: usize = 8 + 2 + 4;
+
+// 内部节点能容纳的子节点数量，内部节点只保存 key，不保存 value
+pub fn optimal_fanout(page_size: usize, avg_key: usize, _avg_val: usize) -> usize {
+    page_size.saturating_sub(HEADER) / (KV_OVERHEAD + avg_key)
+}
+
+// 叶子节点能容纳的 k-v 数量
+pub fn optimal_leaf_entries(page_size: usize, avg_key: usize, avg_val: usize) -> usize {
+    page_size.saturating_sub(HEADER) / (KV_OVERHEAD + avg_key + avg_val)
+}
+
+// 叶子节点中只有哨兵，哨兵中保存着索引目录时不算
+fn only_sentinel(leaf: &BNode) -> bool {
+    leaf.nkeys() == 1 && leaf.get_key_ref(0).is_empty() && leaf.get_val_ref(0).is_empty()
+}
+
+impl<C: KeyComparator> Drop for BTree<C> {
+    fn drop(&mut self) {
+        if self.config.durability == Durability::CommitOnDrop && self.is_dirty() {
+            let _ = self.commit();
+        }
+    }
 }
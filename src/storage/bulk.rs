@@ -0,0 +1,129 @@
+use super::{
+    b_tree::{BNode, BTree, NodeType, HEADER},
+    comparator::KeyComparator,
+    error::BTreeError,
+};
+
+// 构建节点时的一个 k-v，内部节点的 val 为空，ptr 指向子节点
+struct Entry {
+    key: Vec<u8>,
+    val: Vec<u8>,
+    ptr: u64,
+    overflow: bool,
+}
+
+impl Entry {
+    fn size(&self) -> usize {
+        8 + 2 + 4 + self.key.len() + self.val.len()
+    }
+}
+
+impl<C: KeyComparator> BTree<C> {
+    // 从有序的 k-v 构建一棵新树，只能用于空树，哨兵中的索引目录保留在新的哨兵中
+    // 叶子节点按顺序尽量装满，然后自底向上构建内部节点，每个 page 只写入一次
+    // key 不是严格递增时返回 UnsortedKeys，出错时已经写入的 page 不会被释放，
+    // 需要时可以在 Transaction 中调用，通过回滚释放
+    pub fn bulk_load(
+        &mut self,
+        sorted: impl Iterator<Item = (Vec<u8>, Vec<u8>)>,
+    ) -> Result<(), BTreeError> {
+        if !self.is_empty() {
+            return Err(BTreeError::NotEmpty);
+        }
+        let old_root = self.root_ptr();
+        let catalog = match old_root {
+            0 => vec![],
+            ptr => self.get(ptr)?.get_val(0),
+        };
+
+        let mut level = vec![];
+        // 第一个叶子节点以空 key 哨兵开头
+        let sentinel = Entry {
+            key: vec![],
+            val: catalog,
+            ptr: 0,
+            overflow: false,
+        };
+        let mut leaf_size = HEADER + sentinel.size();
+        let mut leaf = vec![sentinel];
+        let mut prev: Option<Vec<u8>> = None;
+        for (key, val) in sorted {
+            if key.is_empty() {
+                return Err(BTreeError::EmptyKey);
+            }
+            if key.len() > self.config().max_key_size {
+                return Err(BTreeError::KeyTooLong);
+            }
+            if prev.is_some_and(|prev| self.comparator().compare(&prev, &key).is_ge()) {
+                return Err(BTreeError::UnsortedKeys);
+            }
+            prev = Some(key.clone());
+
+            let (val, overflow) = self.encode_val(val)?;
+            let entry = Entry {
+                key,
+                val,
+                ptr: 0,
+                overflow,
+            };
+            if leaf_size + entry.size() > self.config().page_size {
+                level.push(self.write_node(NodeType::Leaf, &leaf)?);
+                leaf.clear();
+                leaf_size = HEADER;
+            }
+            leaf_size += entry.size();
+            leaf.push(entry);
+        }
+        // 没有任何 key 时保持空树
+        if prev.is_none() {
+            return Ok(());
+        }
+        if !leaf.is_empty() {
+            level.push(self.write_node(NodeType::Leaf, &leaf)?);
+        }
+
+        // 每一层的节点作为上一层的 k-v，直到只剩一个根节点
+        while level.len() > 1 {
+            let mut parents = vec![];
+            let mut kids = vec![];
+            let mut size = HEADER;
+            for entry in level {
+                if size + entry.size() > self.config().page_size {
+                    parents.push(self.write_node(NodeType::Node, &kids)?);
+                    kids.clear();
+                    size = HEADER;
+                }
+                size += entry.size();
+                kids.push(entry);
+            }
+            parents.push(self.write_node(NodeType::Node, &kids)?);
+            level = parents;
+        }
+
+        self.set_root(level[0].ptr);
+        if old_root != 0 {
+            self.del(old_root);
+        }
+        self.auto_commit()
+    }
+
+    // 写入一个节点，返回它在上一层中的 k-v
+    fn write_node(&mut self, btype: NodeType, entries: &[Entry]) -> Result<Entry, BTreeError> {
+        let mut node = BNode::new(self.config().page_size);
+        node.set_header(btype as u16, entries.len() as u16);
+        for (i, entry) in entries.iter().enumerate() {
+            let idx = i as u16;
+            node.node_append_kv(idx, entry.ptr, entry.key.clone(), entry.val.clone())?;
+            if entry.overflow {
+                node.set_overflow(idx);
+            }
+        }
+
+        Ok(Entry {
+            key: entries[0].key.clone(),
+            val: vec![],
+            ptr: self.new(&node)?,
+            overflow: false,
+        })
+    }
+}
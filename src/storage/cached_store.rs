@@ -0,0 +1,136 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+};
+
+use super::{
+    b_tree::BNode,
+    checksum::seal_page,
+    error::BTreeError,
+    page_store::{PageStore, StoreStats},
+};
+
+// 在 page store 之前缓存最近访问的 page
+// 由于是 copy-on-write，page 写入之后不会再被修改，只需要在 free 时移除
+pub struct CachedStore<S: PageStore> {
+    store: S,
+    capacity: usize,
+    // get 只有 &self，缓存需要内部可变
+    cache: Mutex<Lru>,
+}
+
+// ptr -> (page, 最近一次访问的时间)，order 按访问时间排序，第一个最久没有被访问
+#[derive(Default)]
+struct Lru {
+    pages: HashMap<u64, (BNode, u64)>,
+    order: BTreeMap<u64, u64>,
+    clock: u64,
+}
+
+impl Lru {
+    fn get(&mut self, ptr: u64) -> Option<BNode> {
+        let (node, used) = self.pages.get_mut(&ptr)?;
+        self.order.remove(used);
+        self.clock += 1;
+        *used = self.clock;
+        self.order.insert(self.clock, ptr);
+        Some(node.clone())
+    }
+
+    fn insert(&mut self, ptr: u64, node: BNode, capacity: usize) {
+        self.remove(ptr);
+        while self.pages.len() >= capacity {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                return;
+            };
+            self.pages.remove(&oldest);
+        }
+
+        self.clock += 1;
+        self.pages.insert(ptr, (node, self.clock));
+        self.order.insert(self.clock, ptr);
+    }
+
+    fn remove(&mut self, ptr: u64) {
+        if let Some((_, used)) = self.pages.remove(&ptr) {
+            self.order.remove(&used);
+        }
+    }
+}
+
+impl<S: PageStore> CachedStore<S> {
+    pub fn new(store: S, capacity: usize) -> Self {
+        CachedStore {
+            store,
+            capacity,
+            cache: Mutex::new(Lru::default()),
+        }
+    }
+
+    // 缓存中的 page 数量
+    pub fn len(&self) -> usize {
+        self.cache.lock().unwrap().pages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, ptr: u64) -> bool {
+        self.cache.lock().unwrap().pages.contains_key(&ptr)
+    }
+}
+
+impl<S: PageStore> PageStore for CachedStore<S> {
+    fn get(&self, ptr: u64) -> Result<BNode, BTreeError> {
+        if let Some(node) = self.cache.lock().unwrap().get(ptr) {
+            return Ok(node);
+        }
+
+        let node = self.store.get(ptr)?;
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(ptr, node.clone(), self.capacity);
+        Ok(node)
+    }
+
+    // 缓存的内容与从 store 中读取的相同
+    fn alloc(&mut self, node: &BNode) -> Result<u64, BTreeError> {
+        let ptr = self.store.alloc(node)?;
+
+        let mut page = node.data[..self.page_size()].to_vec();
+        seal_page(&mut page);
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(ptr, BNode { data: page }, self.capacity);
+        Ok(ptr)
+    }
+
+    fn free(&mut self, ptr: u64) {
+        self.cache.lock().unwrap().remove(ptr);
+        self.store.free(ptr)
+    }
+
+    fn page_size(&self) -> usize {
+        self.store.page_size()
+    }
+
+    fn root(&self) -> u64 {
+        self.store.root()
+    }
+
+    fn commit(&mut self, root: u64) -> Result<(), BTreeError> {
+        self.store.commit(root)
+    }
+
+    // 底层 store 的操作次数，命中缓存的读取不计入
+    fn stats(&self) -> StoreStats {
+        self.store.stats()
+    }
+
+    fn reset_stats(&self) {
+        self.store.reset_stats()
+    }
+}
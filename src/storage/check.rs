@@ -0,0 +1,100 @@
+use std::collections::HashSet;
+
+use super::{
+    b_tree::{BNode, BTree, NodeType, HEADER},
+    comparator::KeyComparator,
+    error::BTreeError,
+    overflow::VAL_OVERFLOW,
+};
+
+// check() 的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CheckReport {
+    pub nodes: usize,
+    pub leaves: usize,
+    // 空树为 0，只有一个叶子节点时为 1
+    pub height: usize,
+}
+
+impl<C: KeyComparator> BTree<C> {
+    // 遍历整棵树检查结构，任何一个节点不满足条件时返回 CorruptPage
+    // - 节点非空且不超过一个 page
+    // - 节点中的 key 严格递增
+    // - 内部节点的 key 等于对应子节点的第一个 key
+    // - 所有叶子节点的深度相同
+    // - 每个节点只被引用一次，指针形成环时不会无限递归
+    pub fn check(&self) -> Result<CheckReport, BTreeError> {
+        let mut report = CheckReport::default();
+        let mut visited = HashSet::new();
+        if self.root_ptr() != 0 {
+            self.check_node(self.root_ptr(), None, 1, &mut report, &mut visited)?;
+        }
+        Ok(report)
+    }
+
+    fn check_node(
+        &self,
+        ptr: u64,
+        first_key: Option<&[u8]>,
+        depth: usize,
+        report: &mut CheckReport,
+        visited: &mut HashSet<u64>,
+    ) -> Result<(), BTreeError> {
+        if !visited.insert(ptr) {
+            return Err(BTreeError::CorruptPage);
+        }
+        let node = self.get(ptr)?;
+        check_layout(&node, self.config().page_size)?;
+        node.check_key_order(self.comparator())?;
+        if first_key.is_some_and(|key| key != node.get_key_ref(0)) {
+            return Err(BTreeError::CorruptPage);
+        }
+
+        report.nodes += 1;
+        match node.node_type()? {
+            NodeType::Leaf => {
+                if report.leaves == 0 {
+                    report.height = depth;
+                } else if report.height != depth {
+                    return Err(BTreeError::CorruptPage);
+                }
+                report.leaves += 1;
+            }
+            NodeType::Node => {
+                for i in 0..node.nkeys() {
+                    let key = node.get_key_ref(i);
+                    self.check_node(node.get_ptr(i), Some(key), depth + 1, report, visited)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// 检查节点内的布局，在读取 key 之前先确认所有 k-v 都在 page 之内
+fn check_layout(node: &BNode, page_size: usize) -> Result<(), BTreeError> {
+    let nkeys = node.nkeys();
+    if nkeys == 0 || HEADER + 10 * nkeys as usize > page_size {
+        return Err(BTreeError::CorruptPage);
+    }
+    let end = node.kv_pos(nkeys);
+    if end > page_size {
+        return Err(BTreeError::CorruptPage);
+    }
+
+    // 每个 k-v 的长度与 offset 一致
+    for i in 0..nkeys {
+        let pos = node.kv_pos(i);
+        if pos + 4 > end {
+            return Err(BTreeError::CorruptPage);
+        }
+        let key_len = u16::from_le_bytes(node.data[pos..pos + 2].try_into().unwrap());
+        let val_len = u16::from_le_bytes(node.data[pos + 2..pos + 4].try_into().unwrap());
+        let size = 4 + key_len as usize + (val_len & !VAL_OVERFLOW) as usize;
+        if node.get_offset(i) as usize + size != node.get_offset(i + 1) as usize {
+            return Err(BTreeError::CorruptPage);
+        }
+    }
+    Ok(())
+}
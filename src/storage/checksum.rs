@@ -0,0 +1,55 @@
+use super::error::BTreeError;
+
+// CRC32 (IEEE) 查找表
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0_u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut j = 0;
+        while j < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            j += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+pub fn crc32(data: &[u8]) -> u32 {
+    crc32_update(0, data)
+}
+
+// 在已有的 crc 上继续计算
+pub fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for byte in data {
+        crc = CRC32_TABLE[((crc ^ *byte as u32) & 0xff) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+// page 的 [4, 8) 保存 checksum，计算时跳过这 4 个字节
+fn page_checksum(page: &[u8]) -> u32 {
+    crc32_update(crc32(&page[..4]), &page[8..])
+}
+
+// 写入 page 之前计算 checksum
+pub fn seal_page(page: &mut [u8]) {
+    let crc = page_checksum(page);
+    page[4..8].copy_from_slice(&crc.to_le_bytes());
+}
+
+// 读取 page 时检查 checksum
+pub fn verify_page(page: &[u8]) -> Result<(), BTreeError> {
+    let crc = u32::from_le_bytes(page[4..8].try_into().unwrap());
+    if crc != page_checksum(page) {
+        return Err(BTreeError::CorruptPage);
+    }
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use super::error::BTreeError;
+
+// value 的压缩方式
+// 设置了 codec 的树中，每个 value 前面有一个字节表示是否压缩，
+// 压缩之后没有变小的 value 按原样保存
+// 同一棵树在整个生命周期内必须使用相同的 codec
+pub trait ValueCodec: Send + Sync {
+    fn compress(&self, val: &[u8]) -> Vec<u8>;
+    fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, BTreeError>;
+}
+
+pub(crate) const VAL_RAW: u8 = 0;
+pub(crate) const VAL_COMPRESSED: u8 = 1;
+
+// 不压缩
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoCompression;
+
+impl ValueCodec for NoCompression {
+    fn compress(&self, val: &[u8]) -> Vec<u8> {
+        val.to_vec()
+    }
+
+    fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, BTreeError> {
+        Ok(data.to_vec())
+    }
+}
+
+// 游程编码，不依赖其它 crate，适合有大量重复字节的 value
+// | run | byte | run | byte | ...
+// | 1B  |  1B  | 1B  |  1B  |
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RunLength;
+
+impl ValueCodec for RunLength {
+    fn compress(&self, val: &[u8]) -> Vec<u8> {
+        let mut out = vec![];
+        for chunk in val.chunk_by(|a, b| a == b) {
+            for run in chunk.chunks(u8::MAX as usize) {
+                out.push(run.len() as u8);
+                out.push(run[0]);
+            }
+        }
+        out
+    }
+
+    fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, BTreeError> {
+        if !data.len().is_multiple_of(2) {
+            return Err(BTreeError::CorruptPage);
+        }
+        let mut out = vec![];
+        for pair in data.chunks(2) {
+            if pair[0] == 0 {
+                return Err(BTreeError::CorruptPage);
+            }
+            out.extend(std::iter::repeat_n(pair[1], pair[0] as usize));
+        }
+        Ok(out)
+    }
+}
+
+// deflate 压缩
+#[cfg(feature = "flate2")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Deflate;
+
+#[cfg(feature = "flate2")]
+impl ValueCodec for Deflate {
+    fn compress(&self, val: &[u8]) -> Vec<u8> {
+        use std::io::Write;
+
+        let mut encoder =
+            flate2::write::DeflateEncoder::new(vec![], flate2::Compression::default());
+        // 写入 Vec 不会失败
+        encoder.write_all(val).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, BTreeError> {
+        use std::io::Read;
+
+        let mut out = vec![];
+        flate2::read::DeflateDecoder::new(data)
+            .read_to_end(&mut out)
+            .map_err(|_| BTreeError::CorruptPage)?;
+        Ok(out)
+    }
+}
@@ -0,0 +1,19 @@
+use std::cmp::Ordering;
+
+// key 的排序规则
+// 同一棵树在整个生命周期内（包括重新打开已有的文件）必须使用相同的规则，
+// 否则已经写入的节点不再有序，查找和插入的结果都是错误的
+// 空 key 是哨兵，必须排在所有 key 之前
+pub trait KeyComparator: Send + Sync {
+    fn compare(&self, a: &[u8], b: &[u8]) -> Ordering;
+}
+
+// 默认的字节序比较
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ByteOrder;
+
+impl KeyComparator for ByteOrder {
+    fn compare(&self, a: &[u8], b: &[u8]) -> Ordering {
+        a.cmp(b)
+    }
+}
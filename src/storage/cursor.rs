@@ -0,0 +1,246 @@
+use super::{
+    b_tree::{BNode, BTree, NodeType},
+    comparator::{ByteOrder, KeyComparator},
+    error::BTreeError,
+};
+
+// 可以在树中前后移动的游标，创建时不指向任何 key，需要先 seek
+// path 保存从根节点到当前叶子节点的路径，每一层为 (节点, 当前位置)，
+// 在同一个叶子节点内移动不需要读取 page，跨越节点时只重新读取公共祖先之下的部分，
+// seek 也会复用路径上仍然包含目标 key 的节点
+// 移动出错时游标不再指向任何 key
+pub struct Cursor<'a, C: KeyComparator = ByteOrder> {
+    tree: &'a BTree<C>,
+    path: Vec<(BNode, u16)>,
+    // 当前 value 保存在 overflow page 中或者经过压缩时，读出的完整内容
+    val: Option<Vec<u8>>,
+    // 为 false 时游标不指向任何 key，path 仍然可以被之后的 seek 复用
+    valid: bool,
+}
+
+impl<'a, C: KeyComparator> Cursor<'a, C> {
+    fn new(tree: &'a BTree<C>) -> Self {
+        Cursor {
+            tree,
+            path: vec![],
+            val: None,
+            valid: false,
+        }
+    }
+
+    // 当前的 k-v，游标没有指向任何 key 时返回 None
+    pub fn current(&self) -> Option<(&[u8], &[u8])> {
+        if !self.valid {
+            return None;
+        }
+        let (leaf, idx) = self.path.last()?;
+        let val = match &self.val {
+            Some(val) => val.as_slice(),
+            None => leaf.get_val_ref(*idx),
+        };
+        Some((leaf.get_key_ref(*idx), val))
+    }
+
+    // 定位到第一个 >= key 的位置，不存在时返回 false
+    pub fn seek(&mut self, key: &[u8]) -> Result<bool, BTreeError> {
+        self.guard(|cursor| {
+            if !cursor.descend(key)? {
+                return Ok(false);
+            }
+            let (leaf, idx) = cursor.path.last().unwrap();
+            let found = leaf.get_key_ref(*idx);
+            // 哨兵小于所有 key
+            let before = found.is_empty() || cursor.tree.comparator().compare(found, key).is_lt();
+            if before && !cursor.step(false)? {
+                return Ok(false);
+            }
+            cursor.load()
+        })
+    }
+
+    // 定位到最后一个 <= key 的位置，不存在时返回 false
+    pub fn seek_le(&mut self, key: &[u8]) -> Result<bool, BTreeError> {
+        self.guard(|cursor| {
+            if !cursor.descend(key)? {
+                return Ok(false);
+            }
+            // node_lookup_le 不比较第一个 key，可能比 key 大
+            let (leaf, idx) = cursor.path.last().unwrap();
+            let after = cursor
+                .tree
+                .comparator()
+                .compare(leaf.get_key_ref(*idx), key)
+                .is_gt();
+            if after && !cursor.step(true)? {
+                return Ok(false);
+            }
+            // 只有哨兵 <= key
+            if cursor.at_sentinel() {
+                return Ok(false);
+            }
+            cursor.load()
+        })
+    }
+
+    // 移动到下一个 key，已经是最后一个 key 时保持不动并返回 false
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<bool, BTreeError> {
+        self.guard(|cursor| {
+            if !cursor.valid || !cursor.step(false)? {
+                return Ok(false);
+            }
+            cursor.load()
+        })
+    }
+
+    // 移动到上一个 key，已经是第一个 key 时保持不动并返回 false
+    pub fn prev(&mut self) -> Result<bool, BTreeError> {
+        self.guard(|cursor| {
+            if !cursor.valid || !cursor.step(true)? {
+                return Ok(false);
+            }
+            // 不能停在哨兵上，回到原来的位置
+            if cursor.at_sentinel() {
+                cursor.step(false)?;
+                return Ok(false);
+            }
+            cursor.load()
+        })
+    }
+
+    // 出错时清空路径，避免停在不完整的位置
+    fn guard(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<bool, BTreeError>,
+    ) -> Result<bool, BTreeError> {
+        let result = f(self);
+        if result.is_err() {
+            self.path.clear();
+            self.val = None;
+            self.valid = false;
+        }
+        result
+    }
+
+    // 从根节点下降到 key 所在的叶子节点，空树时返回 false
+    // 自顶向下更新每一层的位置，位置不变的节点直接复用，不重新读取 page
+    fn descend(&mut self, key: &[u8]) -> Result<bool, BTreeError> {
+        self.val = None;
+        self.valid = false;
+        if self.tree.root_ptr() == 0 {
+            self.path.clear();
+            return Ok(false);
+        }
+        if self.path.is_empty() {
+            let root = self.tree.get(self.tree.root_ptr())?;
+            self.path.push((root, 0));
+        }
+
+        let mut depth = 0;
+        loop {
+            let last = depth + 1 == self.path.len();
+            let (node, idx) = &mut self.path[depth];
+            let pos = node.node_lookup_le_by(key, self.tree.comparator());
+            let changed = pos != *idx;
+            *idx = pos;
+            if let NodeType::Leaf = node.node_type()? {
+                return Ok(true);
+            }
+
+            if changed || last {
+                let kid = self.tree.get(node.get_ptr(pos))?;
+                self.path.truncate(depth + 1);
+                self.path.push((kid, 0));
+            }
+            depth += 1;
+        }
+    }
+
+    fn at_sentinel(&self) -> bool {
+        let (leaf, idx) = self.path.last().unwrap();
+        leaf.get_key_ref(*idx).is_empty()
+    }
+
+    // 移动一个位置，已经在边界上时不移动并返回 false
+    fn step(&mut self, reverse: bool) -> Result<bool, BTreeError> {
+        let at_edge = self.path.iter().all(|(node, idx)| {
+            if reverse {
+                *idx == 0
+            } else {
+                *idx + 1 == node.nkeys()
+            }
+        });
+        if at_edge {
+            return Ok(false);
+        }
+
+        // 向上找到可以移动的一层，再沿着另一侧的边缘向下
+        while let Some((node, idx)) = self.path.last_mut() {
+            if reverse && *idx > 0 {
+                *idx -= 1;
+                break;
+            }
+            if !reverse && *idx + 1 < node.nkeys() {
+                *idx += 1;
+                break;
+            }
+            self.path.pop();
+        }
+        while let Some((node, idx)) = self.path.last() {
+            if let NodeType::Leaf = node.node_type()? {
+                break;
+            }
+            let kid = self.tree.get(node.get_ptr(*idx))?;
+            let pos = if reverse { kid.nkeys() - 1 } else { 0 };
+            self.path.push((kid, pos));
+        }
+
+        Ok(true)
+    }
+
+    // 读取当前位置不能直接引用的 value
+    fn load(&mut self) -> Result<bool, BTreeError> {
+        let (leaf, idx) = self.path.last().unwrap();
+        self.val = if self.tree.is_plain(leaf, *idx) {
+            None
+        } else {
+            Some(self.tree.leaf_val(leaf, *idx)?)
+        };
+        self.valid = true;
+        Ok(true)
+    }
+}
+
+impl<C: KeyComparator> BTree<C> {
+    pub fn cursor(&self) -> Cursor<'_, C> {
+        Cursor::new(self)
+    }
+
+    // 批量查找，结果与 keys 的顺序一致
+    // 按 key 的顺序依次 seek，相邻的 key 共享路径上的节点，同一个叶子节点只读取一次
+    pub fn get_batch(&self, keys: &[&[u8]]) -> Result<Vec<Option<Vec<u8>>>, BTreeError> {
+        let mut order: Vec<usize> = (0..keys.len()).collect();
+        order.sort_by(|&a, &b| self.comparator().compare(keys[a], keys[b]));
+
+        let mut result: Vec<Option<Vec<u8>>> = vec![None; keys.len()];
+        let mut cursor = self.cursor();
+        let mut prev: Option<usize> = None;
+        for i in order {
+            let key = keys[i];
+            // 重复的 key 排在一起，直接复用结果
+            if let Some(p) = prev.filter(|&p| self.comparator().compare(keys[p], key).is_eq()) {
+                result[i] = result[p].clone();
+                continue;
+            }
+            prev = Some(i);
+            if key.is_empty() || !cursor.seek(key)? {
+                continue;
+            }
+            let (found, val) = cursor.current().unwrap();
+            if self.comparator().compare(found, key).is_eq() {
+                result[i] = Some(val.to_vec());
+            }
+        }
+        Ok(result)
+    }
+}
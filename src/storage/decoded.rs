@@ -0,0 +1,73 @@
+use super::{
+    b_tree::{BNode, NodeType, HEADER},
+    error::BTreeError,
+    overflow::VAL_OVERFLOW,
+};
+
+// 解码之后的节点，用于调试工具和测试数据
+// 启用 serde feature 时可以序列化，例如转换成 JSON
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DecodedNode {
+    pub btype: u16,
+    pub keys: Vec<DecodedKv>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DecodedKv {
+    pub key: Vec<u8>,
+    pub val: Vec<u8>,
+    pub ptr: u64,
+    // val 是指向 overflow page 的引用
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub overflow: bool,
+}
+
+impl From<&BNode> for DecodedNode {
+    fn from(node: &BNode) -> Self {
+        let keys = (0..node.nkeys())
+            .map(|i| DecodedKv {
+                key: node.get_key(i),
+                val: node.get_val(i),
+                ptr: node.get_ptr(i),
+                overflow: node.is_overflow(i),
+            })
+            .collect();
+        DecodedNode {
+            btype: node.btype(),
+            keys,
+        }
+    }
+}
+
+impl DecodedNode {
+    // 重新生成 page 布局，checksum 保持为 0，由 page store 写入时计算
+    // 只要逻辑内容相同，结果与原来的节点逐字节一致（kv 之后的部分都是 0）
+    pub fn to_node(&self, page_size: usize) -> Result<BNode, BTreeError> {
+        NodeType::try_from(self.btype)?;
+        let size = HEADER
+            + self
+                .keys
+                .iter()
+                .map(|kv| 8 + 2 + 4 + kv.key.len() + kv.val.len())
+                .sum::<usize>();
+        let too_long = self.keys.iter().any(|kv| {
+            kv.key.len() >= VAL_OVERFLOW as usize || kv.val.len() >= VAL_OVERFLOW as usize
+        });
+        if size > page_size || too_long {
+            return Err(BTreeError::PageTooLarge);
+        }
+
+        let mut node = BNode::new(page_size);
+        node.set_header(self.btype, self.keys.len() as u16);
+        for (i, kv) in self.keys.iter().enumerate() {
+            let idx = i as u16;
+            node.node_append_kv(idx, kv.ptr, kv.key.clone(), kv.val.clone())?;
+            if kv.overflow {
+                node.set_overflow(idx);
+            }
+        }
+        Ok(node)
+    }
+}
@@ -0,0 +1,115 @@
+use std::{
+    io::{self, BufWriter, Read, Write},
+    path::Path,
+};
+
+use super::{
+    atomic_file::write_atomic,
+    b_tree::{BTree, KeyValue},
+    comparator::KeyComparator,
+    error::BTreeError,
+    page_store::PageStore,
+};
+
+// 与 page 布局无关的导出格式，按 key 的顺序保存所有 k-v
+// | klen | key | vlen | val | ...
+// |  4B  | ... |  4B  | ... |
+impl<C: KeyComparator> BTree<C> {
+    pub fn dump<W: Write>(&self, w: &mut W) -> Result<(), BTreeError> {
+        let mut iter = self.iter();
+        for (key, val) in iter.by_ref() {
+            w.write_all(&(key.len() as u32).to_le_bytes())?;
+            w.write_all(&key)?;
+            w.write_all(&(val.len() as u32).to_le_bytes())?;
+            w.write_all(&val)?;
+        }
+        if let Some(err) = iter.error() {
+            return Err(err.clone());
+        }
+        w.flush()?;
+        Ok(())
+    }
+
+    // 导出到文件，写入过程中出错时原来的文件保持不变
+    pub fn dump_to_path(&self, path: impl AsRef<Path>) -> Result<(), BTreeError> {
+        let mut result = Ok(());
+        let written = write_atomic(path.as_ref(), |fp| {
+            result = self.dump(&mut BufWriter::new(fp));
+            result.clone().map_err(|_| io::ErrorKind::Other.into())
+        });
+        // 优先返回 dump 本身的错误
+        result?;
+        Ok(written?)
+    }
+}
+
+impl BTree {
+    // 从 dump 的输出中重建一棵树，store 必须是空的
+    pub fn restore<R: Read>(store: Box<dyn PageStore>, r: &mut R) -> Result<BTree, BTreeError> {
+        let mut tree = BTree::with_store(store);
+        let config = *tree.config();
+
+        let mut error = None;
+        let records = std::iter::from_fn(|| {
+            let record = read_record(r, config.max_key_size, config.max_blob_size);
+            record.unwrap_or_else(|err| {
+                error = Some(err);
+                None
+            })
+        });
+        let result = tree.bulk_load(records);
+        if let Some(err) = error {
+            return Err(err);
+        }
+        result?;
+
+        Ok(tree)
+    }
+}
+
+// 读取一条记录，在记录之间遇到文件结尾时返回 None
+fn read_record<R: Read>(
+    r: &mut R,
+    max_key: usize,
+    max_val: usize,
+) -> Result<Option<KeyValue>, BTreeError> {
+    let mut len = [0_u8; 4];
+    let n = read_full(r, &mut len)?;
+    if n == 0 {
+        return Ok(None);
+    }
+    if n < len.len() {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+
+    let key_len = u32::from_le_bytes(len) as usize;
+    if key_len > max_key {
+        return Err(BTreeError::KeyTooLong);
+    }
+    let mut key = vec![0; key_len];
+    r.read_exact(&mut key)?;
+
+    r.read_exact(&mut len)?;
+    let val_len = u32::from_le_bytes(len) as usize;
+    if val_len > max_val {
+        return Err(BTreeError::ValueTooLong);
+    }
+    let mut val = vec![0; val_len];
+    r.read_exact(&mut val)?;
+
+    Ok(Some((key, val)))
+}
+
+// 尽量读满 buf，返回读取的字节数
+fn read_full<R: Read>(r: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut n = 0;
+    while n < buf.len() {
+        match r.read(&mut buf[n..]) {
+            Ok(0) => break,
+            Ok(k) => n += k,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(n)
+}
@@ -0,0 +1,50 @@
+use std::{fmt, io};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BTreeError {
+    // 节点类型不是 Node 或 Leaf
+    InvalidNodeType(u16),
+    // 节点超过一个 page
+    PageTooLarge,
+    KeyTooLong,
+    // 空 key 保留为哨兵
+    EmptyKey,
+    ValueTooLong,
+    // page 的内容或指针无效
+    CorruptPage,
+    // page 在 Snapshot 创建之后被释放并重新分配
+    StalePage,
+    // BTreeConfig 无效，或者与 page store 不一致
+    InvalidConfig,
+    // bulk_load 只能用于空树
+    NotEmpty,
+    // bulk_load 的输入不是严格递增的
+    UnsortedKeys,
+    Io(io::ErrorKind),
+}
+
+impl fmt::Display for BTreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BTreeError::InvalidNodeType(btype) => write!(f, "invalid node type {btype}"),
+            BTreeError::PageTooLarge => write!(f, "node does not fit in a page"),
+            BTreeError::KeyTooLong => write!(f, "key is too long"),
+            BTreeError::EmptyKey => write!(f, "key is empty"),
+            BTreeError::ValueTooLong => write!(f, "value is too long"),
+            BTreeError::CorruptPage => write!(f, "corrupt page"),
+            BTreeError::StalePage => write!(f, "page was reused after the snapshot was taken"),
+            BTreeError::InvalidConfig => write!(f, "invalid btree config"),
+            BTreeError::NotEmpty => write!(f, "tree is not empty"),
+            BTreeError::UnsortedKeys => write!(f, "keys are not sorted"),
+            BTreeError::Io(kind) => write!(f, "io error: {kind}"),
+        }
+    }
+}
+
+impl std::error::Error for BTreeError {}
+
+impl From<io::Error> for BTreeError {
+    fn from(err: io::Error) -> Self {
+        BTreeError::Io(err.kind())
+    }
+}
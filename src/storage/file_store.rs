@@ -0,0 +1,725 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File, OpenOptions},
+    io::Write,
+    os::unix::fs::FileExt,
+    path::{Path, PathBuf},
+    ptr,
+};
+
+use super::{
+    atomic_file::write_atomic,
+    b_tree::{BNode, NodeType, BTREE_PAGE_SIZE},
+    checksum::{crc32, seal_page, verify_page},
+    error::BTreeError,
+    index::decode_catalog,
+    overflow::{parse_ref, OVERFLOW_HEADER, OVERFLOW_TYPE},
+    page_store::{PageStore, StatCounters, StoreStats},
+    wal::{append_wal, read_wal, truncate_wal, WalRecord},
+};
+
+const MASTER_SIG: &[u8; 16] = b"BuildYourOwnDB07";
+
+// master page
+// | sig | root | npages | free list | checksum |
+// | 16B |  8B  |   8B   |     8B    |    4B    |
+const MASTER_SIZE: usize = 44;
+
+// free list page，seq 为写入这条链表的提交序号，恢复时用来区分新旧
+// | type | count | checksum | next | seq | pointers  |
+// |  2B  |   2B  |    4B    |  8B  | 8B  | count * 8B |
+const FREE_LIST_TYPE: u16 = 3;
+const FREE_LIST_HEADER: usize = 24;
+const FREE_LIST_CAP: usize = (BTREE_PAGE_SIZE - FREE_LIST_HEADER) / 8;
+
+// 每次扩展文件的最小 page 数量
+const GROW_PAGES: u64 = 64;
+
+// 基于 mmap 的 page store
+// 数据文件中第 i 个 page 的指针为 i，page 0 保留为空指针
+// master page 保存在单独的文件中，通过 fsync + rename 原子地更新
+//
+// 空闲的 page 保存在 free list 中，alloc 优先复用空闲的 page
+// 由于是 copy-on-write，当前事务释放的 page 仍然被上一次提交的树引用，
+// 所以先放入 freed，提交之后才能被复用；当前事务自己分配又释放的 page 除外
+// free list 本身以链表的形式保存在空闲的 page 中
+//
+// 启用日志时，数据 page fsync 之后、更新 master page 之前先写一条日志记录，
+// 打开时根据日志重放没有完成的提交
+pub struct FileStore {
+    path: PathBuf,
+    file: File,
+    // mmap 映射的区域
+    mmap: *mut u8,
+    mmap_size: usize,
+    // 已经写入文件的 page 数量
+    flushed: u64,
+    // 尚未写入文件的新 page
+    pending: Vec<Vec<u8>>,
+    // 复用空闲 page 时写入的内容，提交时写入文件
+    updates: HashMap<u64, Vec<u8>>,
+    // 可以复用的 page
+    free_list: Vec<u64>,
+    // 当前事务释放的 page，提交后才能复用
+    freed: Vec<u64>,
+    // 当前事务分配的 page，它们没有被已提交的树引用，释放后可以立即复用
+    allocated: HashSet<u64>,
+    root: u64,
+    wal: bool,
+    // 最近一次写入的 free list 的序号
+    seq: u64,
+    counters: StatCounters,
+}
+
+// mmap 是只读的映射，只在 &mut self 的方法中重新映射，
+// 所以可以像普通的只读数据一样在线程之间共享
+unsafe impl Send for FileStore {}
+unsafe impl Sync for FileStore {}
+
+impl FileStore {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, BTreeError> {
+        Self::open_with(path.as_ref(), false)
+    }
+
+    // 每次提交都先写日志
+    pub fn open_with_wal(path: impl AsRef<Path>) -> Result<Self, BTreeError> {
+        Self::open_with(path.as_ref(), true)
+    }
+
+    // 无论是否启用日志，打开时都会处理遗留的日志
+    fn open_with(path: &Path, wal: bool) -> Result<Self, BTreeError> {
+        let mut store = Self::open_file(path, wal)?;
+        let mut free_head = store.read_master()?;
+        let replayed = store.replay_wal(&mut free_head)?;
+
+        let mut file_size = store.file.metadata()?.len();
+        if file_size == 0 {
+            file_size = BTREE_PAGE_SIZE as u64;
+            store.file.set_len(file_size)?;
+        }
+        if file_size < store.flushed * BTREE_PAGE_SIZE as u64 {
+            return Err(BTreeError::CorruptPage);
+        }
+        store.map(file_size as usize)?;
+        store.read_free_list(free_head)?;
+
+        // 重放的提交释放的 page 都应当在 free list 中
+        if let Some(freed) = replayed {
+            let free: HashSet<_> = store.free_list.iter().collect();
+            if !freed.iter().all(|ptr| free.contains(ptr)) {
+                return Err(BTreeError::CorruptPage);
+            }
+        }
+        truncate_wal(&store.wal_path())?;
+
+        Ok(store)
+    }
+
+    // 打开数据文件，还没有读取 master page
+    fn open_file(path: &Path, wal: bool) -> Result<Self, BTreeError> {
+        let path = path.to_path_buf();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+
+        Ok(FileStore {
+            path,
+            file,
+            mmap: ptr::null_mut(),
+            mmap_size: 0,
+            flushed: 1,
+            pending: vec![],
+            updates: HashMap::new(),
+            free_list: vec![],
+            freed: vec![],
+            allocated: HashSet::new(),
+            root: 0,
+            wal,
+            seq: 0,
+            counters: StatCounters::default(),
+        })
+    }
+
+    // master page 损坏时，扫描数据文件找回根节点，重建 free list 之后重新写入 master page
+    // master page 完好时与 open 相同
+    //
+    // 根节点是没有被其他节点引用、并且整棵子树都通过检查的节点。由于是 copy-on-write，
+    // 文件中可能还留着旧版本的根节点，有多个候选时只保留与某条 free list 恰好
+    // 划分所有 page 的那个，仍然无法确定唯一的根节点时返回 CorruptPage。
+    // 索引树的 page 通过根节点哨兵中的目录找到，与主树一起算作存活
+    pub fn recover(path: impl AsRef<Path>) -> Result<Self, BTreeError> {
+        let path = path.as_ref();
+        let mut store = Self::open_file(path, false)?;
+        match store.read_master() {
+            Ok(_) => return Self::open(path),
+            Err(BTreeError::CorruptPage) => {}
+            Err(err) => return Err(err),
+        }
+
+        let mut npages = store.file.metadata()?.len() / BTREE_PAGE_SIZE as u64;
+        if npages == 0 {
+            npages = 1;
+            store.file.set_len(BTREE_PAGE_SIZE as u64)?;
+        }
+        store.flushed = npages;
+        store.map((npages as usize) * BTREE_PAGE_SIZE)?;
+
+        // 文件末尾预留的空间全是 0，不属于任何提交
+        let mut pages = HashMap::new();
+        let mut last = 0;
+        for ptr in 1..npages {
+            let data = store.read_page(ptr);
+            if data.iter().any(|b| *b != 0) {
+                last = ptr;
+            }
+            if verify_page(&data).is_ok() {
+                pages.insert(ptr, BNode { data });
+            }
+        }
+        store.flushed = last + 1;
+
+        let (seq, root) = store.find_root(&pages)?;
+        let reachable = tree_pages(&pages, root).unwrap();
+        store.root = root;
+        store.seq = seq;
+        store.free_list = (1..store.flushed)
+            .filter(|ptr| !reachable.contains(ptr))
+            .collect();
+        store.flush(root)?;
+        truncate_wal(&store.wal_path())?;
+        Ok(store)
+    }
+
+    // 在所有通过校验的 page 中找出唯一的根节点，空树为 0，同时返回对应的 free list 的序号
+    fn find_root(&self, pages: &HashMap<u64, BNode>) -> Result<(u64, u64), BTreeError> {
+        // 以哨兵开头并且整棵子树都一致的节点，包括左侧路径上的内部节点
+        let subtrees: Vec<(u64, HashSet<u64>)> = pages
+            .iter()
+            .filter(|(_, page)| {
+                NodeType::try_from(page.btype()).is_ok()
+                    && page.nkeys() > 0
+                    && page.get_key_ref(0).is_empty()
+            })
+            .filter_map(|(ptr, _)| Some((*ptr, tree_pages(pages, *ptr)?)))
+            .collect();
+        // 没有被其他候选引用的才是根节点，索引树的根节点被主树引用，不是候选
+        let candidates: Vec<&(u64, HashSet<u64>)> = subtrees
+            .iter()
+            .filter(|(root, _)| {
+                !subtrees
+                    .iter()
+                    .any(|(other, live)| other != root && live.contains(root))
+            })
+            .collect();
+
+        // 每次提交之后，存活的 page、free list 自身以及其中记录的 page 恰好划分整个文件，
+        // 空树也作为一个候选。旧版本的根节点可能与当时的 free list 也满足这个条件，
+        // 这时取序号最大的一组
+        let lists = free_lists(pages);
+        let empty = (0, HashSet::new());
+        let mut exact: Vec<(u64, u64)> = vec![];
+        for (root, live) in candidates.iter().copied().chain([&empty]) {
+            for (seq, list) in &lists {
+                let free: HashSet<u64> = list.iter().copied().collect();
+                let partition = free.len() == list.len()
+                    && live.len() + free.len() + 1 == self.flushed as usize
+                    && free
+                        .iter()
+                        .all(|ptr| (1..self.flushed).contains(ptr) && !live.contains(ptr));
+                if partition {
+                    exact.push((*seq, *root));
+                }
+            }
+        }
+        exact.sort();
+        exact.dedup();
+        if let [.., (a, _), (b, _)] = exact.as_slice() {
+            if a == b {
+                return Err(BTreeError::CorruptPage);
+            }
+        }
+
+        // free list 也损坏时，只有一个候选才能确定
+        match (exact.last(), candidates.as_slice()) {
+            (Some((seq, root)), _) => Ok((*seq, *root)),
+            (None, [(root, _)]) => Ok((0, *root)),
+            // 所有 page 都完好并且其中没有任何节点才是空树
+            (None, [])
+                if pages.len() + 1 == self.flushed as usize
+                    && pages
+                        .values()
+                        .all(|page| NodeType::try_from(page.btype()).is_err()) =>
+            {
+                Ok((0, 0))
+            }
+            _ => Err(BTreeError::CorruptPage),
+        }
+    }
+
+    // 数据文件中的 page 数量，包括尚未写入的 page
+    pub fn page_count(&self) -> u64 {
+        self.flushed + self.pending.len() as u64
+    }
+
+    // 空闲的 page 数量，包括当前事务释放的 page
+    pub fn free_count(&self) -> usize {
+        self.free_list.len() + self.freed.len()
+    }
+
+    fn master_path(&self) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".master");
+        PathBuf::from(path)
+    }
+
+    fn wal_path(&self) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".wal");
+        PathBuf::from(path)
+    }
+
+    // 日志中有完整的记录而 master page 仍是旧的，说明提交在更新 master page 之前中断，
+    // 此时数据 page 已经写入，直接重放，返回这次提交释放的 page
+    // 不完整的记录说明提交没有生效，保持 master page 中的状态
+    fn replay_wal(&mut self, free_head: &mut u64) -> Result<Option<Vec<u64>>, BTreeError> {
+        let Some(record) = read_wal(&self.wal_path())? else {
+            return Ok(None);
+        };
+        if (self.root, self.flushed, *free_head)
+            == (record.new_root, record.npages, record.free_head)
+        {
+            return Ok(None);
+        }
+        if self.root != record.old_root
+            || record.npages < self.flushed
+            || record.new_root >= record.npages
+            || record.free_head >= record.npages
+        {
+            return Err(BTreeError::CorruptPage);
+        }
+
+        self.root = record.new_root;
+        self.flushed = record.npages;
+        *free_head = record.free_head;
+        self.write_master(record.free_head)?;
+        Ok(Some(record.freed))
+    }
+
+    // 读取 master page，返回 free list 的第一个 page
+    fn read_master(&mut self) -> Result<u64, BTreeError> {
+        let data = match fs::read(self.master_path()) {
+            Ok(data) => data,
+            // 新建的数据库
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(err) => return Err(err.into()),
+        };
+
+        if data.len() < MASTER_SIZE || &data[..16] != MASTER_SIG {
+            return Err(BTreeError::CorruptPage);
+        }
+        let crc = u32::from_le_bytes(data[40..44].try_into().unwrap());
+        if crc != crc32(&data[..40]) {
+            return Err(BTreeError::CorruptPage);
+        }
+        let root = u64::from_le_bytes(data[16..24].try_into().unwrap());
+        let npages = u64::from_le_bytes(data[24..32].try_into().unwrap());
+        let free_head = u64::from_le_bytes(data[32..40].try_into().unwrap());
+        if npages < 1 || root >= npages || free_head >= npages {
+            return Err(BTreeError::CorruptPage);
+        }
+
+        self.root = root;
+        self.flushed = npages;
+        Ok(free_head)
+    }
+
+    // 读取 free list，链表自身占用的 page 在下一次提交之后才能复用
+    // 没有空闲 page 时文件中也不会有旧的链表，序号可以从 0 开始
+    fn read_free_list(&mut self, mut head: u64) -> Result<(), BTreeError> {
+        while head != 0 {
+            let page = self.read_page(head);
+            verify_page(&page)?;
+            let btype = u16::from_le_bytes(page[0..2].try_into().unwrap());
+            let count = u16::from_le_bytes(page[2..4].try_into().unwrap()) as usize;
+            if btype != FREE_LIST_TYPE || count > FREE_LIST_CAP {
+                return Err(BTreeError::CorruptPage);
+            }
+            if self.freed.is_empty() {
+                self.seq = u64::from_le_bytes(page[16..24].try_into().unwrap());
+            }
+
+            for i in 0..count {
+                let pos = FREE_LIST_HEADER + 8 * i;
+                let ptr = u64::from_le_bytes(page[pos..pos + 8].try_into().unwrap());
+                if ptr == 0 || ptr >= self.flushed {
+                    return Err(BTreeError::CorruptPage);
+                }
+                self.free_list.push(ptr);
+            }
+
+            self.freed.push(head);
+            let next = u64::from_le_bytes(page[8..16].try_into().unwrap());
+            if next >= self.flushed || self.freed.len() as u64 > self.flushed {
+                return Err(BTreeError::CorruptPage);
+            }
+            head = next;
+        }
+
+        Ok(())
+    }
+
+    // 将所有空闲的 page 写成新的 free list，返回链表的第一个 page
+    // 链表占用的 page 从已提交的空闲 page 中取，不足时在文件末尾分配，
+    // 不能使用当前事务释放的 page，因为上一次提交的 free list 可能还在其中
+    fn write_free_list(&mut self) -> u64 {
+        let mut entries = std::mem::take(&mut self.freed);
+        let mut list_pages = vec![];
+        loop {
+            let total = self.free_list.len() + entries.len();
+            if list_pages.len() * FREE_LIST_CAP >= total {
+                break;
+            }
+            match self.free_list.pop() {
+                Some(ptr) => list_pages.push(ptr),
+                None => {
+                    list_pages.push(self.page_count());
+                    self.pending.push(vec![0; BTREE_PAGE_SIZE]);
+                }
+            }
+        }
+        entries.append(&mut self.free_list);
+        self.seq += 1;
+
+        let mut next = 0_u64;
+        for (i, ptr) in list_pages.iter().enumerate().rev() {
+            let chunk = &entries[(i * FREE_LIST_CAP).min(entries.len())
+                ..((i + 1) * FREE_LIST_CAP).min(entries.len())];
+
+            let mut page = vec![0_u8; BTREE_PAGE_SIZE];
+            page[0..2].copy_from_slice(&FREE_LIST_TYPE.to_le_bytes());
+            page[2..4].copy_from_slice(&(chunk.len() as u16).to_le_bytes());
+            page[8..16].copy_from_slice(&next.to_le_bytes());
+            page[16..24].copy_from_slice(&self.seq.to_le_bytes());
+            for (j, free) in chunk.iter().enumerate() {
+                let pos = FREE_LIST_HEADER + 8 * j;
+                page[pos..pos + 8].copy_from_slice(&free.to_le_bytes());
+            }
+            seal_page(&mut page);
+            self.write_page(*ptr, page);
+            next = *ptr;
+        }
+
+        // 提交之后，所有空闲的 page 都可以复用，
+        // 而这次写入的链表在下一次提交之后也不再被引用
+        self.free_list = entries;
+        self.freed = list_pages;
+        next
+    }
+
+    fn read_page(&self, ptr: u64) -> Vec<u8> {
+        if let Some(page) = self.updates.get(&ptr) {
+            return page.clone();
+        }
+        if ptr >= self.flushed {
+            return self.pending[(ptr - self.flushed) as usize].clone();
+        }
+
+        let offset = ptr as usize * BTREE_PAGE_SIZE;
+        let page = unsafe { std::slice::from_raw_parts(self.mmap.add(offset), BTREE_PAGE_SIZE) };
+        page.to_vec()
+    }
+
+    fn write_page(&mut self, ptr: u64, page: Vec<u8>) {
+        if ptr >= self.flushed {
+            self.pending[(ptr - self.flushed) as usize] = page;
+        } else {
+            self.updates.insert(ptr, page);
+        }
+    }
+
+    // 先写入临时文件并 fsync，再 rename 覆盖 master page
+    fn write_master(&self, free_head: u64) -> Result<(), BTreeError> {
+        let mut data = [0_u8; MASTER_SIZE];
+        data[..16].copy_from_slice(MASTER_SIG);
+        data[16..24].copy_from_slice(&self.root.to_le_bytes());
+        data[24..32].copy_from_slice(&self.flushed.to_le_bytes());
+        data[32..40].copy_from_slice(&free_head.to_le_bytes());
+        let crc = crc32(&data[..40]);
+        data[40..44].copy_from_slice(&crc.to_le_bytes());
+
+        write_atomic(&self.master_path(), |fp| fp.write_all(&data))?;
+        Ok(())
+    }
+
+    // 映射 [0, size) 的文件内容
+    fn map(&mut self, size: usize) -> Result<(), BTreeError> {
+        self.unmap();
+
+        let mmap = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                size,
+                libc::PROT_READ,
+                libc::MAP_SHARED,
+                std::os::unix::io::AsRawFd::as_raw_fd(&self.file),
+                0,
+            )
+        };
+        if mmap == libc::MAP_FAILED {
+            return Err(std::io::Error::last_os_error().into());
+        }
+
+        self.mmap = mmap as *mut u8;
+        self.mmap_size = size;
+        Ok(())
+    }
+
+    fn unmap(&mut self) {
+        if !self.mmap.is_null() {
+            unsafe {
+                libc::munmap(self.mmap as *mut libc::c_void, self.mmap_size);
+            }
+            self.mmap = ptr::null_mut();
+            self.mmap_size = 0;
+        }
+    }
+
+    // 扩展文件使其能容纳 npages 个 page，每次至少扩展一倍
+    fn extend(&mut self, npages: u64) -> Result<(), BTreeError> {
+        let size = npages as usize * BTREE_PAGE_SIZE;
+        if size <= self.mmap_size {
+            return Ok(());
+        }
+
+        let mut new_size = self.mmap_size.max(GROW_PAGES as usize * BTREE_PAGE_SIZE);
+        while new_size < size {
+            new_size *= 2;
+        }
+        self.file.set_len(new_size as u64)?;
+        self.map(new_size)
+    }
+
+    // 将新 page 和 free list 写入文件，然后更新 master page
+    fn flush(&mut self, root: u64) -> Result<(), BTreeError> {
+        let free_head = self.prepare_commit(root)?;
+        self.write_master(free_head)?;
+        if self.wal {
+            truncate_wal(&self.wal_path())?;
+        }
+        Ok(())
+    }
+
+    // 提交的前半部分: 写入数据 page 并 fsync，启用日志时再写入日志记录，
+    // 返回 free list 的第一个 page，之后只需要更新 master page
+    pub(crate) fn prepare_commit(&mut self, root: u64) -> Result<u64, BTreeError> {
+        let old_root = std::mem::replace(&mut self.root, root);
+        let freed = self.freed.clone();
+        let free_head = self.write_free_list();
+        let npages = self.page_count();
+        self.extend(npages)?;
+
+        for (i, page) in self.pending.iter().enumerate() {
+            let offset = (self.flushed + i as u64) * BTREE_PAGE_SIZE as u64;
+            self.file.write_all_at(page, offset)?;
+        }
+        for (ptr, page) in self.updates.iter() {
+            self.file.write_all_at(page, ptr * BTREE_PAGE_SIZE as u64)?;
+        }
+        self.file.sync_all()?;
+
+        self.flushed = npages;
+        self.pending.clear();
+        self.updates.clear();
+        self.allocated.clear();
+
+        if self.wal {
+            let record = WalRecord {
+                old_root,
+                new_root: root,
+                npages,
+                free_head,
+                freed,
+            };
+            append_wal(&self.wal_path(), &record)?;
+        }
+        Ok(free_head)
+    }
+}
+
+// root 之下所有 page 的集合，包括 overflow page
+// 引用的 page 不存在、类型不对、被引用多次、叶子节点不在同一层，
+// 或者内部节点中的 key 与子节点的第一个 key 不一致时返回 None
+fn reachable(pages: &HashMap<u64, BNode>, root: u64) -> Option<HashSet<u64>> {
+    let mut live = HashSet::new();
+    if root == 0 {
+        return Some(live);
+    }
+    // 第一个叶子节点以哨兵开头
+    let mut stack = vec![(root, vec![], 0)];
+    let mut leaf_depth = None;
+    while let Some((ptr, first, depth)) = stack.pop() {
+        let page = pages.get(&ptr)?;
+        let btype = NodeType::try_from(page.btype()).ok()?;
+        if !live.insert(ptr) || page.nkeys() == 0 || page.get_key_ref(0) != first.as_slice() {
+            return None;
+        }
+        match btype {
+            NodeType::Node => {
+                for i in 0..page.nkeys() {
+                    stack.push((page.get_ptr(i), page.get_key(i), depth + 1));
+                }
+            }
+            NodeType::Leaf => {
+                if *leaf_depth.get_or_insert(depth) != depth {
+                    return None;
+                }
+                for i in (0..page.nkeys()).filter(|&i| page.is_overflow(i)) {
+                    let (len, mut next) = parse_ref(page.get_val_ref(i)).ok()?;
+                    for _ in 0..len.div_ceil(BTREE_PAGE_SIZE - OVERFLOW_HEADER) {
+                        let overflow = pages.get(&next)?;
+                        if overflow.btype() != OVERFLOW_TYPE || !live.insert(next) {
+                            return None;
+                        }
+                        next = u64::from_le_bytes(
+                            overflow.data[8..OVERFLOW_HEADER].try_into().unwrap(),
+                        );
+                    }
+                }
+            }
+        }
+    }
+    Some(live)
+}
+
+// root 之下的 page 加上哨兵中记录的各个索引树的 page，两者不能重叠
+// 目录无法解析或者索引树不一致时返回 None
+fn tree_pages(pages: &HashMap<u64, BNode>, root: u64) -> Option<HashSet<u64>> {
+    let mut live = reachable(pages, root)?;
+    if root == 0 {
+        return Some(live);
+    }
+    let mut leaf = &pages[&root];
+    while leaf.btype() == NodeType::Node as u16 {
+        leaf = &pages[&leaf.get_ptr(0)];
+    }
+    if leaf.is_overflow(0) {
+        return None;
+    }
+    for (_, index_root) in decode_catalog(leaf.get_val_ref(0)).ok()? {
+        for ptr in reachable(pages, index_root)? {
+            if !live.insert(ptr) {
+                return None;
+            }
+        }
+    }
+    Some(live)
+}
+
+// 文件中每条完整的 free list 的序号，以及链表自身的 page 和其中记录的空闲 page
+// 链表中的 page 序号都相同，没有空闲 page 时为 (0, [])
+fn free_lists(pages: &HashMap<u64, BNode>) -> Vec<(u64, Vec<u64>)> {
+    let field =
+        |page: &BNode, pos: usize| u64::from_le_bytes(page.data[pos..pos + 8].try_into().unwrap());
+    let list_page = |ptr: u64| {
+        pages
+            .get(&ptr)
+            .filter(|page| page.btype() == FREE_LIST_TYPE)
+    };
+
+    let mut lists = vec![(0, vec![])];
+    for &head in pages.keys().filter(|ptr| list_page(**ptr).is_some()) {
+        let seq = field(&pages[&head], 16);
+        let mut list = vec![];
+        let mut ptr = head;
+        while ptr != 0 {
+            let Some(page) = list_page(ptr).filter(|page| field(page, 16) == seq) else {
+                list.clear();
+                break;
+            };
+            if list.len() > pages.len() * FREE_LIST_CAP {
+                list.clear();
+                break;
+            }
+            let count = u16::from_le_bytes(page.data[2..4].try_into().unwrap()) as usize;
+            list.push(ptr);
+            list.extend(
+                (0..count.min(FREE_LIST_CAP)).map(|i| field(page, FREE_LIST_HEADER + 8 * i)),
+            );
+            ptr = field(page, 8);
+        }
+        if !list.is_empty() {
+            lists.push((seq, list));
+        }
+    }
+    lists
+}
+
+impl PageStore for FileStore {
+    fn get(&self, ptr: u64) -> Result<BNode, BTreeError> {
+        self.counters.get();
+        if ptr == 0 || ptr >= self.page_count() {
+            return Err(BTreeError::CorruptPage);
+        }
+
+        let page = self.read_page(ptr);
+        verify_page(&page)?;
+        Ok(BNode { data: page })
+    }
+
+    fn alloc(&mut self, node: &BNode) -> Result<u64, BTreeError> {
+        if node.n_bytes() as usize > BTREE_PAGE_SIZE {
+            return Err(BTreeError::PageTooLarge);
+        }
+
+        let mut page = node.data[..BTREE_PAGE_SIZE].to_vec();
+        seal_page(&mut page);
+        let ptr = match self.free_list.pop() {
+            Some(ptr) => {
+                self.write_page(ptr, page);
+                ptr
+            }
+            None => {
+                self.pending.push(page);
+                self.page_count() - 1
+            }
+        };
+
+        self.allocated.insert(ptr);
+        self.counters.alloc();
+        Ok(ptr)
+    }
+
+    fn free(&mut self, ptr: u64) {
+        self.counters.free();
+        if self.allocated.remove(&ptr) {
+            self.free_list.push(ptr);
+        } else {
+            self.freed.push(ptr);
+        }
+    }
+
+    fn root(&self) -> u64 {
+        self.root
+    }
+
+    fn commit(&mut self, root: u64) -> Result<(), BTreeError> {
+        self.flush(root)
+    }
+
+    fn stats(&self) -> StoreStats {
+        self.counters.load()
+    }
+
+    fn reset_stats(&self) {
+        self.counters.reset()
+    }
+}
+
+impl Drop for FileStore {
+    fn drop(&mut self) {
+        self.unmap();
+    }
+}
@@ -0,0 +1,278 @@
+use std::ops::Bound;
+
+use super::{
+    b_tree::{BNode, BTree, NodeType, Update},
+    comparator::KeyComparator,
+    error::BTreeError,
+    scan::{prefix_end, ScanIter},
+};
+
+// 二级索引，从 value 中提取索引的 key，返回 None 时这条记录不进入索引
+//
+// 每个索引是同一个 store 中的另一棵树，key 为 | 索引 key 的长度 2B | 索引 key | 主键 |，
+// 所以同一个索引 key 可以对应多个主键。各个索引的根节点按名字保存在主树哨兵的 value 中，
+// 随主树的根节点一起提交，在 Transaction 中回滚时索引也一起恢复
+pub struct Index {
+    name: String,
+    extract: Box<Extract>,
+}
+
+type Extract = dyn Fn(&[u8]) -> Option<Vec<u8>> + Send + Sync;
+
+impl Index {
+    pub fn new(
+        name: impl Into<String>,
+        extract: impl Fn(&[u8]) -> Option<Vec<u8>> + Send + Sync + 'static,
+    ) -> Self {
+        Index {
+            name: name.into(),
+            extract: Box::new(extract),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+// 索引的名字和根节点，按名字排序，不包括空的索引
+// | name len | name | root | ...
+// |    2B    | ...  |  8B  |
+type Catalog = Vec<(String, u64)>;
+
+// 一个索引中要删除和插入的索引项
+struct IndexChange<'a> {
+    name: &'a str,
+    remove: Option<Vec<u8>>,
+    add: Option<Vec<u8>>,
+}
+
+impl BTree {
+    // 插入或更新 k-v，同时更新 indexes 中的每个索引
+    // 更新时根据旧的 value 删除旧的索引项，调用者每次都需要传入相同的 indexes
+    // 任何一个索引项或者新的目录无效时返回错误，主树和索引都不修改，
+    // 之后的写入出错时也回滚到修改之前，在 Transaction 中则由 Transaction 回滚
+    pub fn insert_indexed(
+        &mut self,
+        key: &[u8],
+        val: &[u8],
+        indexes: &[Index],
+    ) -> Result<(), BTreeError> {
+        let old = self.get_value(key)?;
+        let changes = self.index_changes(key, old.as_deref(), Some(val), indexes)?;
+        self.atomic(|tree| {
+            tree.insert_value(key, val)?;
+            tree.apply_index_changes(changes)
+        })?;
+        self.auto_commit()
+    }
+
+    // 删除 key 以及它在 indexes 中的索引项
+    pub fn delete_indexed(&mut self, key: &[u8], indexes: &[Index]) -> Result<bool, BTreeError> {
+        let Some(old) = self.get_value(key)? else {
+            return Ok(false);
+        };
+        let changes = self.index_changes(key, Some(&old), None, indexes)?;
+        let found = self.atomic(|tree| {
+            tree.apply_index_changes(changes)?;
+            tree.delete_key(key)
+        })?;
+        self.auto_commit()?;
+        Ok(found)
+    }
+
+    // 按顺序返回索引 key 为 index_key 的所有主键，索引不存在时为空
+    pub fn index_scan(&self, name: &str, index_key: &[u8]) -> Result<Vec<Vec<u8>>, BTreeError> {
+        let Some(root) = self.index_root(name)? else {
+            return Ok(vec![]);
+        };
+
+        let prefix = index_prefix(index_key)?;
+        let end = prefix_end(&prefix);
+        let end = match &end {
+            Some(end) => Bound::Excluded(end.as_slice()),
+            None => Bound::Unbounded,
+        };
+        let mut iter = ScanIter::new(self, root, Bound::Included(&prefix), end, false);
+        let keys = iter
+            .by_ref()
+            .map(|(key, _)| key[prefix.len()..].to_vec())
+            .collect();
+        match iter.error() {
+            Some(err) => Err(err.clone()),
+            None => Ok(keys),
+        }
+    }
+
+    fn index_root(&self, name: &str) -> Result<Option<u64>, BTreeError> {
+        let catalog = self.catalog()?;
+        Ok(catalog
+            .iter()
+            .find(|(entry, _)| entry == name)
+            .map(|(_, root)| *root))
+    }
+
+    // 在修改之前构造每个索引要删除和插入的索引项，并检查长度，
+    // 以及加入新的索引之后目录是否仍然能放进哨兵
+    fn index_changes<'a>(
+        &self,
+        key: &[u8],
+        old: Option<&[u8]>,
+        new: Option<&[u8]>,
+        indexes: &'a [Index],
+    ) -> Result<Vec<IndexChange<'a>>, BTreeError> {
+        let mut changes = vec![];
+        for index in indexes {
+            let old_key = old.and_then(|old| (index.extract)(old));
+            let new_key = new.and_then(|new| (index.extract)(new));
+            if old_key == new_key {
+                continue;
+            }
+            changes.push(IndexChange {
+                name: &index.name,
+                remove: old_key.map(|k| self.checked_entry(&k, key)).transpose()?,
+                add: new_key.map(|k| self.checked_entry(&k, key)).transpose()?,
+            });
+        }
+
+        let mut catalog = self.catalog()?;
+        for change in changes.iter().filter(|change| change.add.is_some()) {
+            if !catalog.iter().any(|(name, _)| name == change.name) {
+                catalog.push((change.name.to_string(), 0));
+            }
+        }
+        if encode_catalog(&catalog).len() > self.config().max_val_size {
+            return Err(BTreeError::ValueTooLong);
+        }
+        Ok(changes)
+    }
+
+    fn apply_index_changes(&mut self, changes: Vec<IndexChange>) -> Result<(), BTreeError> {
+        for change in changes {
+            if let Some(entry) = change.remove {
+                self.update_index(change.name, |tree| tree.delete_key(&entry).map(|_| ()))?;
+            }
+            if let Some(entry) = change.add {
+                self.update_index(change.name, |tree| tree.insert_value(&entry, b""))?;
+            }
+        }
+        Ok(())
+    }
+
+    // 索引项是索引树中的 key，同样受 max_key_size 限制
+    fn checked_entry(&self, index_key: &[u8], key: &[u8]) -> Result<Vec<u8>, BTreeError> {
+        let entry = index_entry(index_key, key)?;
+        if entry.len() > self.config().max_key_size {
+            return Err(BTreeError::KeyTooLong);
+        }
+        Ok(entry)
+    }
+
+    // 临时将根节点切换到索引树上执行 f，再把新的索引根节点写回目录
+    fn update_index(
+        &mut self,
+        name: &str,
+        f: impl FnOnce(&mut Self) -> Result<(), BTreeError>,
+    ) -> Result<(), BTreeError> {
+        let mut catalog = self.catalog()?;
+        let pos = catalog.iter().position(|(entry, _)| entry == name);
+        let index_root = pos.map_or(0, |pos| catalog[pos].1);
+
+        let primary = self.root_ptr();
+        self.set_root(index_root);
+        let result = f(self);
+        let index_root = self.root_ptr();
+        self.set_root(primary);
+        result?;
+
+        match (pos, index_root) {
+            (Some(pos), 0) => {
+                catalog.remove(pos);
+            }
+            (Some(pos), root) => catalog[pos].1 = root,
+            (None, 0) => return Ok(()),
+            (None, root) => {
+                catalog.push((name.to_string(), root));
+                catalog.sort();
+            }
+        }
+        self.set_catalog(&catalog)
+    }
+}
+
+impl<C: KeyComparator> BTree<C> {
+    // 读取主树哨兵中的索引目录
+    pub(crate) fn catalog(&self) -> Result<Catalog, BTreeError> {
+        if self.root_ptr() == 0 {
+            return Ok(vec![]);
+        }
+        let mut node = self.get(self.root_ptr())?;
+        while let NodeType::Node = node.node_type()? {
+            node = self.get(node.get_ptr(0))?;
+        }
+        if !node.get_key_ref(0).is_empty() {
+            return Err(BTreeError::CorruptPage);
+        }
+        decode_catalog(node.get_val_ref(0))
+    }
+
+    // 目录必须能直接放在第一个叶子节点中
+    fn set_catalog(&mut self, catalog: &Catalog) -> Result<(), BTreeError> {
+        let data = encode_catalog(catalog);
+        if data.len() > self.config().max_val_size {
+            return Err(BTreeError::ValueTooLong);
+        }
+        if self.root_ptr() != 0 {
+            return self.insert_update(b"", Update::Raw(&data));
+        }
+
+        let mut root = BNode::new(self.config().page_size);
+        root.set_header(NodeType::Leaf as u16, 1);
+        root.node_append_kv(0, 0, vec![], data)?;
+        let ptr = self.new(&root)?;
+        self.set_root(ptr);
+        Ok(())
+    }
+}
+
+fn index_prefix(index_key: &[u8]) -> Result<Vec<u8>, BTreeError> {
+    let len = u16::try_from(index_key.len()).map_err(|_| BTreeError::KeyTooLong)?;
+    let mut prefix = len.to_be_bytes().to_vec();
+    prefix.extend_from_slice(index_key);
+    Ok(prefix)
+}
+
+fn index_entry(index_key: &[u8], key: &[u8]) -> Result<Vec<u8>, BTreeError> {
+    let mut entry = index_prefix(index_key)?;
+    entry.extend_from_slice(key);
+    Ok(entry)
+}
+
+fn encode_catalog(catalog: &Catalog) -> Vec<u8> {
+    let mut data = vec![];
+    for (name, root) in catalog {
+        data.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        data.extend_from_slice(name.as_bytes());
+        data.extend_from_slice(&root.to_le_bytes());
+    }
+    data
+}
+
+pub(crate) fn decode_catalog(mut data: &[u8]) -> Result<Catalog, BTreeError> {
+    let mut catalog = vec![];
+    while !data.is_empty() {
+        if data.len() < 2 {
+            return Err(BTreeError::CorruptPage);
+        }
+        let len = u16::from_le_bytes([data[0], data[1]]) as usize;
+        if data.len() < 2 + len + 8 {
+            return Err(BTreeError::CorruptPage);
+        }
+        let name =
+            String::from_utf8(data[2..2 + len].to_vec()).map_err(|_| BTreeError::CorruptPage)?;
+        let root = u64::from_le_bytes(data[2 + len..2 + len + 8].try_into().unwrap());
+        catalog.push((name, root));
+        data = &data[2 + len + 8..];
+    }
+    Ok(catalog)
+}
@@ -1 +1,24 @@
+pub mod arena;
+pub mod atomic_file;
 pub mod b_tree;
+pub mod bulk;
+pub mod cached_store;
+pub mod check;
+pub mod checksum;
+pub mod codec;
+pub mod comparator;
+pub mod cursor;
+pub mod decoded;
+pub mod dump;
+pub mod error;
+pub mod file_store;
+pub mod index;
+pub mod key_codec;
+pub mod overflow;
+pub mod page_store;
+pub mod scan;
+pub mod shared;
+pub mod snapshot;
+pub mod stats;
+pub mod transaction;
+pub mod wal;
@@ -0,0 +1,109 @@
+use super::{
+    b_tree::{BNode, BTree, HEADER},
+    comparator::KeyComparator,
+    error::BTreeError,
+};
+
+// overflow page，nkeys 始终为 0，保存较大 value 的一部分
+// | type | nkeys | checksum | next | data |
+// |  2B  |   2B  |    4B    |  8B  | ...  |
+pub const OVERFLOW_TYPE: u16 = 4;
+pub(crate) const OVERFLOW_HEADER: usize = HEADER + 8;
+
+// 叶子节点中保存的 overflow 引用
+// | len | head |
+// | 8B  |  8B  |
+pub const OVERFLOW_REF_SIZE: usize = 16;
+
+// vlen 的最高位，表示 value 是 overflow 引用
+pub const VAL_OVERFLOW: u16 = 1 << 15;
+
+impl<C: KeyComparator> BTree<C> {
+    fn overflow_cap(&self) -> usize {
+        self.config().page_size - OVERFLOW_HEADER
+    }
+
+    // 将 val 写入 overflow page 链表，返回保存在叶子节点中的引用
+    pub(crate) fn write_overflow(&mut self, val: &[u8]) -> Result<Vec<u8>, BTreeError> {
+        // 从尾部开始分配，这样每个 page 都知道下一个 page 的位置
+        let mut next = 0_u64;
+        for chunk in val.chunks(self.overflow_cap()).rev() {
+            let mut page = BNode::new(self.config().page_size);
+            page.set_header(OVERFLOW_TYPE, 0);
+            page.data[HEADER..OVERFLOW_HEADER].copy_from_slice(&next.to_le_bytes());
+            page.data[OVERFLOW_HEADER..OVERFLOW_HEADER + chunk.len()].copy_from_slice(chunk);
+            next = self.new(&page)?;
+        }
+
+        let mut reference = Vec::with_capacity(OVERFLOW_REF_SIZE);
+        reference.extend_from_slice(&(val.len() as u64).to_le_bytes());
+        reference.extend_from_slice(&next.to_le_bytes());
+        Ok(reference)
+    }
+
+    // 按引用读取 generation 时刻的 overflow page 链表，拼接出完整的 value
+    pub(crate) fn read_overflow(
+        &self,
+        reference: &[u8],
+        generation: u64,
+    ) -> Result<Vec<u8>, BTreeError> {
+        let (len, mut ptr) = self.checked_ref(reference)?;
+
+        let mut val = Vec::with_capacity(len);
+        while val.len() < len {
+            self.check_generation(ptr, generation)?;
+            let page = self.overflow_page(ptr)?;
+            let n = (len - val.len()).min(self.overflow_cap());
+            val.extend_from_slice(&page.data[OVERFLOW_HEADER..OVERFLOW_HEADER + n]);
+            ptr = next_ptr(&page);
+        }
+
+        Ok(val)
+    }
+
+    // 释放引用指向的所有 overflow page
+    pub(crate) fn free_overflow(&mut self, reference: &[u8]) -> Result<(), BTreeError> {
+        let (len, mut ptr) = self.checked_ref(reference)?;
+
+        for _ in 0..len.div_ceil(self.overflow_cap()) {
+            let page = self.overflow_page(ptr)?;
+            self.del(ptr);
+            ptr = next_ptr(&page);
+        }
+
+        Ok(())
+    }
+
+    // 写入时 value 不超过 max_blob_size，更长的 len 来自损坏的 page，不能按它分配内存
+    fn checked_ref(&self, reference: &[u8]) -> Result<(usize, u64), BTreeError> {
+        let (len, ptr) = parse_ref(reference)?;
+        if len > self.config().max_blob_size {
+            return Err(BTreeError::CorruptPage);
+        }
+        Ok((len, ptr))
+    }
+
+    fn overflow_page(&self, ptr: u64) -> Result<BNode, BTreeError> {
+        if ptr == 0 {
+            return Err(BTreeError::CorruptPage);
+        }
+        let page = self.get_page(ptr)?;
+        if page.btype() != OVERFLOW_TYPE {
+            return Err(BTreeError::CorruptPage);
+        }
+        Ok(page)
+    }
+}
+
+pub(crate) fn parse_ref(reference: &[u8]) -> Result<(usize, u64), BTreeError> {
+    if reference.len() != OVERFLOW_REF_SIZE {
+        return Err(BTreeError::CorruptPage);
+    }
+    let len = u64::from_le_bytes(reference[..8].try_into().unwrap()) as usize;
+    let head = u64::from_le_bytes(reference[8..16].try_into().unwrap());
+    Ok((len, head))
+}
+
+fn next_ptr(page: &BNode) -> u64 {
+    u64::from_le_bytes(page.data[HEADER..OVERFLOW_HEADER].try_into().unwrap())
+}
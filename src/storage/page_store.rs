@@ -0,0 +1,172 @@
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use super::{
+    b_tree::{BNode, BTREE_PAGE_SIZE},
+    checksum::{seal_page, verify_page},
+    error::BTreeError,
+};
+
+// page 的分配、读取和释放，BTree 通过它来访问节点
+// 写入 page 时计算 checksum，读取时检查，不一致时返回 CorruptPage
+// 要求 Send + Sync，以便 BTree 可以在线程之间共享
+pub trait PageStore: Send + Sync {
+    // 读取 page
+    fn get(&self, ptr: u64) -> Result<BNode, BTreeError>;
+
+    // 分配一个新的 page 并写入节点，返回 page 指针
+    fn alloc(&mut self, node: &BNode) -> Result<u64, BTreeError>;
+
+    // 释放 page
+    fn free(&mut self, ptr: u64);
+
+    // page 的大小，必须与 BTreeConfig 一致
+    fn page_size(&self) -> usize {
+        BTREE_PAGE_SIZE
+    }
+
+    // 最近一次提交的根节点
+    fn root(&self) -> u64 {
+        0
+    }
+
+    // 持久化所有新 page，并将 root 记录为根节点
+    fn commit(&mut self, _root: u64) -> Result<(), BTreeError> {
+        Ok(())
+    }
+
+    // 创建或者上一次 reset_stats 之后的操作次数，不统计的 store 全为 0
+    fn stats(&self) -> StoreStats {
+        StoreStats::default()
+    }
+
+    fn reset_stats(&self) {}
+}
+
+// page 的读取、分配和释放次数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StoreStats {
+    pub gets: u64,
+    pub allocs: u64,
+    pub frees: u64,
+}
+
+// store 内部的计数器，get 只有 &self，所以使用原子变量
+#[derive(Debug, Default)]
+pub(crate) struct StatCounters {
+    gets: AtomicU64,
+    allocs: AtomicU64,
+    frees: AtomicU64,
+}
+
+impl StatCounters {
+    pub(crate) fn get(&self) {
+        self.gets.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn alloc(&self) {
+        self.allocs.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn free(&self) {
+        self.frees.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn load(&self) -> StoreStats {
+        StoreStats {
+            gets: self.gets.load(Ordering::Relaxed),
+            allocs: self.allocs.load(Ordering::Relaxed),
+            frees: self.frees.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn reset(&self) {
+        self.gets.store(0, Ordering::Relaxed);
+        self.allocs.store(0, Ordering::Relaxed);
+        self.frees.store(0, Ordering::Relaxed);
+    }
+}
+
+// 基于内存的 page store，指针 0 保留为空指针
+#[derive(Debug)]
+pub struct MemoryStore {
+    pages: HashMap<u64, Vec<u8>>,
+    next: u64,
+    page_size: usize,
+    counters: StatCounters,
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::with_page_size(BTREE_PAGE_SIZE)
+    }
+
+    pub fn with_page_size(page_size: usize) -> Self {
+        MemoryStore {
+            pages: HashMap::new(),
+            next: 1,
+            page_size,
+            counters: StatCounters::default(),
+        }
+    }
+
+    // 当前存活的 page 数量
+    pub fn len(&self) -> usize {
+        self.pages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+    }
+}
+
+impl PageStore for MemoryStore {
+    fn get(&self, ptr: u64) -> Result<BNode, BTreeError> {
+        self.counters.get();
+        let page = self.pages.get(&ptr).ok_or(BTreeError::CorruptPage)?;
+        verify_page(page)?;
+
+        Ok(BNode { data: page.clone() })
+    }
+
+    fn alloc(&mut self, node: &BNode) -> Result<u64, BTreeError> {
+        if node.n_bytes() as usize > self.page_size {
+            return Err(BTreeError::PageTooLarge);
+        }
+
+        let mut page = node.data[..self.page_size].to_vec();
+        seal_page(&mut page);
+
+        let ptr = self.next;
+        self.next += 1;
+        self.pages.insert(ptr, page);
+        self.counters.alloc();
+
+        Ok(ptr)
+    }
+
+    fn free(&mut self, ptr: u64) {
+        assert!(self.pages.remove(&ptr).is_some(), "page {ptr} not found");
+        self.counters.free();
+    }
+
+    fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    fn stats(&self) -> StoreStats {
+        self.counters.load()
+    }
+
+    fn reset_stats(&self) {
+        self.counters.reset()
+    }
+}
@@ -0,0 +1,288 @@
+use std::ops::Bound;
+
+use super::{
+    b_tree::{BNode, BTree, KeyValue, NodeType},
+    comparator::ByteOrder,
+    comparator::KeyComparator,
+    error::BTreeError,
+};
+
+// 按 key 顺序遍历叶子节点的迭代器，reverse 时从大到小
+// path 保存从根节点到当前叶子节点的路径，每一层为 (节点, 当前位置)
+// 读取 page 出错时迭代结束，错误可以通过 error() 获取
+pub struct ScanIter<'a, C: KeyComparator = ByteOrder> {
+    tree: &'a BTree<C>,
+    path: Vec<(BNode, u16)>,
+    // 迭代结束的边界，正向时为 end，反向时为 start
+    limit: Bound<Vec<u8>>,
+    reverse: bool,
+    // 只读取这个 generation 时刻的树中的 page
+    generation: u64,
+    error: Option<BTreeError>,
+}
+
+impl<'a, C: KeyComparator> ScanIter<'a, C> {
+    pub(crate) fn new(
+        tree: &'a BTree<C>,
+        root: u64,
+        start: Bound<&[u8]>,
+        end: Bound<&[u8]>,
+        reverse: bool,
+    ) -> Self {
+        Self::new_at(tree, root, tree.generation, start, end, reverse)
+    }
+
+    // 遍历 generation 时刻以 root 为根的树，用于 Snapshot
+    pub(crate) fn new_at(
+        tree: &'a BTree<C>,
+        root: u64,
+        generation: u64,
+        start: Bound<&[u8]>,
+        end: Bound<&[u8]>,
+        reverse: bool,
+    ) -> Self {
+        let (from, limit) = if reverse { (end, start) } else { (start, end) };
+        let mut iter = ScanIter {
+            tree,
+            path: vec![],
+            limit: limit.map(|key| key.to_vec()),
+            reverse,
+            generation,
+            error: None,
+        };
+        if let Err(err) = iter.seek(root, from) {
+            iter.fail(err);
+        }
+        iter
+    }
+
+    // 迭代过程中遇到的错误
+    pub fn error(&self) -> Option<&BTreeError> {
+        self.error.as_ref()
+    }
+
+    fn fail(&mut self, err: BTreeError) {
+        self.path.clear();
+        self.error = Some(err);
+    }
+
+    // 定位到第一个满足 from 的 key，from 在反向时是上界
+    fn seek(&mut self, root: u64, from: Bound<&[u8]>) -> Result<(), BTreeError> {
+        if root == 0 {
+            return Ok(());
+        }
+
+        let mut node = self.tree.get_at(root, self.generation)?;
+        loop {
+            let idx = match from {
+                Bound::Included(key) | Bound::Excluded(key) => {
+                    node.node_lookup_le_by(key, self.tree.comparator())
+                }
+                Bound::Unbounded => self.first_pos(&node),
+            };
+
+            match node.node_type()? {
+                NodeType::Leaf => {
+                    self.path.push((node, idx));
+                    break;
+                }
+                NodeType::Node => {
+                    let kid = self.tree.get_at(node.get_ptr(idx), self.generation)?;
+                    self.path.push((node, idx));
+                    node = kid;
+                }
+            }
+        }
+
+        // node_lookup_le 返回的是 <= from 的位置，需要跳过不满足条件的 key
+        let (leaf, idx) = self.path.last().unwrap();
+        let key = leaf.get_key_ref(*idx);
+        let cmp = self.tree.comparator();
+        let skip = match (self.reverse, from) {
+            (false, Bound::Included(start)) => cmp.compare(key, start).is_lt(),
+            (false, Bound::Excluded(start)) => cmp.compare(key, start).is_le(),
+            (true, Bound::Included(end)) => cmp.compare(key, end).is_gt(),
+            (true, Bound::Excluded(end)) => cmp.compare(key, end).is_ge(),
+            (_, Bound::Unbounded) => false,
+        };
+        if skip {
+            self.step()?;
+        }
+
+        Ok(())
+    }
+
+    // 进入节点时的位置
+    fn first_pos(&self, node: &BNode) -> u16 {
+        if self.reverse {
+            node.nkeys() - 1
+        } else {
+            0
+        }
+    }
+
+    // 移动到下一个 key，跨越叶子节点时从公共祖先重新向下
+    fn step(&mut self) -> Result<(), BTreeError> {
+        while let Some((node, idx)) = self.path.last_mut() {
+            if self.reverse && *idx > 0 {
+                *idx -= 1;
+                break;
+            }
+            if !self.reverse && *idx + 1 < node.nkeys() {
+                *idx += 1;
+                break;
+            }
+            self.path.pop();
+        }
+
+        while let Some((node, idx)) = self.path.last() {
+            if let NodeType::Leaf = node.node_type()? {
+                break;
+            }
+            let kid = self.tree.get_at(node.get_ptr(*idx), self.generation)?;
+            let pos = self.first_pos(&kid);
+            self.path.push((kid, pos));
+        }
+
+        Ok(())
+    }
+}
+
+impl<C: KeyComparator> Iterator for ScanIter<'_, C> {
+    type Item = (Vec<u8>, Vec<u8>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // 跳过最左边叶子节点中的哨兵
+        loop {
+            let (leaf, idx) = self.path.last()?;
+            if !leaf.get_key_ref(*idx).is_empty() {
+                break;
+            }
+            if let Err(err) = self.step() {
+                self.fail(err);
+                return None;
+            }
+        }
+
+        let (leaf, idx) = self.path.last()?;
+        let key = leaf.get_key_ref(*idx);
+
+        let cmp = self.tree.comparator();
+        let in_range = match (self.reverse, &self.limit) {
+            (false, Bound::Included(end)) => cmp.compare(key, end).is_le(),
+            (false, Bound::Excluded(end)) => cmp.compare(key, end).is_lt(),
+            (true, Bound::Included(start)) => cmp.compare(key, start).is_ge(),
+            (true, Bound::Excluded(start)) => cmp.compare(key, start).is_gt(),
+            (_, Bound::Unbounded) => true,
+        };
+        if !in_range {
+            self.path.clear();
+            return None;
+        }
+
+        let key = key.to_vec();
+        let val = match self.tree.leaf_val_at(leaf, *idx, self.generation) {
+            Ok(val) => val,
+            Err(err) => {
+                self.fail(err);
+                return None;
+            }
+        };
+        if let Err(err) = self.step() {
+            self.fail(err);
+        }
+        Some((key, val))
+    }
+}
+
+impl<C: KeyComparator> BTree<C> {
+    // 按顺序返回 [start, end] 范围内的 k-v
+    pub fn scan(&self, start: Bound<&[u8]>, end: Bound<&[u8]>) -> ScanIter<'_, C> {
+        ScanIter::new(self, self.root_ptr(), start, end, false)
+    }
+
+    // 按顺序返回所有 k-v
+    pub fn iter(&self) -> ScanIter<'_, C> {
+        self.scan(Bound::Unbounded, Bound::Unbounded)
+    }
+
+    // 从大到小返回所有 k-v
+    pub fn iter_rev(&self) -> ScanIter<'_, C> {
+        ScanIter::new(
+            self,
+            self.root_ptr(),
+            Bound::Unbounded,
+            Bound::Unbounded,
+            true,
+        )
+    }
+
+    // 按顺序返回 [start, end) 范围内最多 limit 个 k-v，取够之后不再继续遍历
+    pub fn get_range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+        limit: usize,
+    ) -> Result<Vec<KeyValue>, BTreeError> {
+        if limit == 0 || self.comparator().compare(start, end).is_ge() {
+            return Ok(vec![]);
+        }
+
+        let mut iter = self.scan(Bound::Included(start), Bound::Excluded(end));
+        let items = iter.by_ref().take(limit).collect();
+        match iter.error() {
+            Some(err) => Err(err.clone()),
+            None => Ok(items),
+        }
+    }
+
+    // key 的数量，需要遍历所有节点
+    pub fn len(&self) -> Result<usize, BTreeError> {
+        if self.root_ptr() == 0 {
+            return Ok(0);
+        }
+        self.count_keys(self.root_ptr())
+    }
+
+    // 与 len 一致，不计算哨兵，哨兵中可能保存着索引目录，
+    // 所以只剩下哨兵的叶子根节点也是空树。读取根节点出错时不为空
+    pub fn is_empty(&self) -> bool {
+        self.root_ptr() == 0
+            || self.get(self.root_ptr()).is_ok_and(|root| {
+                root.btype() == NodeType::Leaf as u16
+                    && root.nkeys() == 1
+                    && root.get_key_ref(0).is_empty()
+            })
+    }
+
+    fn count_keys(&self, ptr: u64) -> Result<usize, BTreeError> {
+        let node = self.get(ptr)?;
+        match node.node_type()? {
+            // 不计算哨兵
+            NodeType::Leaf => Ok(node.nkeys() as usize - node.get_key_ref(0).is_empty() as usize),
+            NodeType::Node => (0..node.nkeys())
+                .map(|i| self.count_keys(node.get_ptr(i)))
+                .sum(),
+        }
+    }
+}
+
+impl BTree {
+    // 所有以 prefix 开头的 k-v，范围是 [prefix, prefix 的后继)
+    // 上界按字节序计算，所以只用于默认的 comparator
+    pub fn scan_prefix(&self, prefix: &[u8]) -> ScanIter<'_> {
+        match prefix_end(prefix) {
+            Some(end) => self.scan(Bound::Included(prefix), Bound::Excluded(&end)),
+            None => self.scan(Bound::Included(prefix), Bound::Unbounded),
+        }
+    }
+}
+
+// 大于所有以 prefix 开头的 key 的最小 key：去掉末尾的 0xff，再将最后一个字节加一
+// prefix 为空或者全是 0xff 时没有上界
+pub fn prefix_end(prefix: &[u8]) -> Option<Vec<u8>> {
+    let last = prefix.iter().rposition(|&b| b != 0xff)?;
+    let mut end = prefix[..=last].to_vec();
+    end[last] += 1;
+    Some(end)
+}
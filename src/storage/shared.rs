@@ -0,0 +1,68 @@
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use super::{
+    b_tree::BTree,
+    comparator::{ByteOrder, KeyComparator},
+    error::BTreeError,
+};
+
+// 可以在线程之间共享的 BTree，多个线程可以同时读，写操作互斥
+//
+// 一致性：每次写操作(包括 write 中的整个闭包)在持有写锁时完成，
+// 读者总是看到某次写操作完成之后的根节点，不会看到写了一半的树。
+// 由于是 copy-on-write，写操作只会分配新的 page 并在最后切换根节点
+pub struct SharedBTree<C: KeyComparator = ByteOrder> {
+    tree: Arc<RwLock<BTree<C>>>,
+}
+
+// derive 会要求 C: Clone
+impl<C: KeyComparator> Clone for SharedBTree<C> {
+    fn clone(&self) -> Self {
+        SharedBTree {
+            tree: self.tree.clone(),
+        }
+    }
+}
+
+impl<C: KeyComparator> SharedBTree<C> {
+    pub fn new(tree: BTree<C>) -> Self {
+        SharedBTree {
+            tree: Arc::new(RwLock::new(tree)),
+        }
+    }
+
+    // 持有读锁执行 f，f 中的多次读取看到的是同一棵树
+    pub fn read<R>(&self, f: impl FnOnce(&BTree<C>) -> R) -> R {
+        f(&self.read_lock())
+    }
+
+    // 持有写锁执行 f，f 中的所有修改对读者同时可见
+    pub fn write<R>(&self, f: impl FnOnce(&mut BTree<C>) -> R) -> R {
+        f(&mut self.write_lock())
+    }
+
+    pub fn get_value(&self, key: &[u8]) -> Result<Option<Vec<u8>>, BTreeError> {
+        self.read(|tree| tree.get_value(key))
+    }
+
+    pub fn insert(&self, key: &[u8], val: &[u8]) -> Result<(), BTreeError> {
+        self.write(|tree| tree.insert(key, val))
+    }
+
+    pub fn delete(&self, key: &[u8]) -> Result<bool, BTreeError> {
+        self.write(|tree| tree.delete(key))
+    }
+
+    pub fn commit(&self) -> Result<(), BTreeError> {
+        self.write(|tree| tree.commit())
+    }
+
+    // 其它线程在持有锁时 panic 不会破坏树，旧的根节点仍然有效
+    fn read_lock(&self) -> RwLockReadGuard<'_, BTree<C>> {
+        self.tree.read().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn write_lock(&self) -> RwLockWriteGuard<'_, BTree<C>> {
+        self.tree.write().unwrap_or_else(|err| err.into_inner())
+    }
+}
@@ -0,0 +1,76 @@
+use std::{
+    ops::Bound,
+    sync::{Arc, Weak},
+};
+
+use super::{b_tree::BTree, comparator::KeyComparator, error::BTreeError, scan::ScanIter};
+
+// 某一时刻的只读视图
+// 由于是 copy-on-write，旧的根节点及其子树不会被修改，
+// 只需要保证 Snapshot 存活期间这些 page 不被释放
+//
+// 不固定 page 的 Snapshot 不阻止释放，读到被重新分配的 page 时返回 StalePage
+pub struct Snapshot {
+    root: u64,
+    // 创建时 BTree 的 generation
+    generation: u64,
+    // 与创建它的 BTree 共享，持有期间 BTree 推迟释放 page，不固定 page 时为 None
+    _pin: Option<Arc<()>>,
+    // 用于检查 Snapshot 和 BTree 是否对应
+    owner: Weak<()>,
+}
+
+impl Snapshot {
+    pub fn root_ptr(&self) -> u64 {
+        self.root
+    }
+
+    pub fn get_value<C: KeyComparator>(
+        &self,
+        tree: &BTree<C>,
+        key: &[u8],
+    ) -> Result<Option<Vec<u8>>, BTreeError> {
+        self.check_tree(tree);
+        tree.get_value_at(self.root, self.generation, key)
+    }
+
+    pub fn scan<'a, C: KeyComparator>(
+        &self,
+        tree: &'a BTree<C>,
+        start: Bound<&[u8]>,
+        end: Bound<&[u8]>,
+    ) -> ScanIter<'a, C> {
+        self.check_tree(tree);
+        ScanIter::new_at(tree, self.root, self.generation, start, end, false)
+    }
+
+    pub fn iter<'a, C: KeyComparator>(&self, tree: &'a BTree<C>) -> ScanIter<'a, C> {
+        self.scan(tree, Bound::Unbounded, Bound::Unbounded)
+    }
+
+    // Snapshot 只能用于创建它的 BTree
+    fn check_tree<C: KeyComparator>(&self, tree: &BTree<C>) {
+        assert!(Weak::ptr_eq(&self.owner, &Arc::downgrade(&tree.snapshots)));
+    }
+}
+
+impl<C: KeyComparator> BTree<C> {
+    // 创建当前根节点的 Snapshot，之后的写入对它不可见
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            _pin: Some(self.snapshots.clone()),
+            ..self.snapshot_unpinned()
+        }
+    }
+
+    // 不阻止旧的 page 被释放，之后的写入不需要推迟释放
+    // 旧的 page 被重新分配之后，读取返回 StalePage 而不是新 page 的内容
+    pub fn snapshot_unpinned(&self) -> Snapshot {
+        Snapshot {
+            root: self.root_ptr(),
+            generation: self.generation,
+            _pin: None,
+            owner: Arc::downgrade(&self.snapshots),
+        }
+    }
+}
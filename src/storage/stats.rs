@@ -0,0 +1,83 @@
+use super::{
+    b_tree::{BTree, NodeType},
+    comparator::KeyComparator,
+    error::BTreeError,
+};
+
+// stats() 的结果
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TreeStats {
+    // 空树为 0，只有一个叶子节点时为 1
+    pub height: usize,
+    pub nodes: usize,
+    pub leaves: usize,
+    pub internal: usize,
+    // 每个节点中 key 的平均数量，包括哨兵
+    pub avg_keys: f64,
+    // 节点占用的字节数 / page 大小的平均值
+    pub avg_fill: f64,
+    // 从根节点开始每一层的统计
+    pub levels: Vec<LevelStats>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LevelStats {
+    pub nodes: usize,
+    pub keys: usize,
+    pub avg_fill: f64,
+    pub min_fill: f64,
+    pub max_fill: f64,
+}
+
+impl<C: KeyComparator> BTree<C> {
+    // 逐层遍历整棵树，统计节点数量和填充率
+    pub fn stats(&self) -> Result<TreeStats, BTreeError> {
+        let mut stats = TreeStats::default();
+        let page_size = self.config().page_size as f64;
+        let (mut total_keys, mut total_fill) = (0, 0.0);
+
+        let mut level = if self.root_ptr() == 0 {
+            vec![]
+        } else {
+            vec![self.root_ptr()]
+        };
+        while !level.is_empty() {
+            let mut next = vec![];
+            let mut ls = LevelStats {
+                min_fill: f64::MAX,
+                ..Default::default()
+            };
+            for ptr in level {
+                let node = self.get(ptr)?;
+                let fill = node.n_bytes() as f64 / page_size;
+                ls.nodes += 1;
+                ls.keys += node.nkeys() as usize;
+                ls.avg_fill += fill;
+                ls.min_fill = ls.min_fill.min(fill);
+                ls.max_fill = ls.max_fill.max(fill);
+
+                match node.node_type()? {
+                    NodeType::Leaf => stats.leaves += 1,
+                    NodeType::Node => {
+                        stats.internal += 1;
+                        next.extend((0..node.nkeys()).map(|i| node.get_ptr(i)));
+                    }
+                }
+            }
+
+            stats.nodes += ls.nodes;
+            total_keys += ls.keys;
+            total_fill += ls.avg_fill;
+            ls.avg_fill /= ls.nodes as f64;
+            stats.levels.push(ls);
+            level = next;
+        }
+
+        stats.height = stats.levels.len();
+        if stats.nodes > 0 {
+            stats.avg_keys = total_keys as f64 / stats.nodes as f64;
+            stats.avg_fill = total_fill / stats.nodes as f64;
+        }
+        Ok(stats)
+    }
+}
@@ -0,0 +1,130 @@
+use std::collections::HashSet;
+
+use super::{
+    b_tree::BTree,
+    comparator::{ByteOrder, KeyComparator},
+    error::BTreeError,
+    index::Index,
+};
+
+// Transaction 期间分配和释放的 page
+#[derive(Debug, Default)]
+pub(crate) struct TxnState {
+    // 本次 Transaction 分配且仍然存活的 page，回滚时释放
+    pub(crate) allocated: HashSet<u64>,
+    // 开始之前就存在的 page，提交时才释放
+    pub(crate) freed: Vec<u64>,
+}
+
+// 写事务，提交时所有修改一起生效，回滚或者未提交就 drop 时全部丢弃
+pub struct Transaction<'a, C: KeyComparator = ByteOrder> {
+    tree: &'a mut BTree<C>,
+    // 开始时的根节点
+    root: u64,
+    done: bool,
+}
+
+impl<C: KeyComparator> Transaction<'_, C> {
+    pub fn insert(&mut self, key: &[u8], val: &[u8]) -> Result<(), BTreeError> {
+        self.tree.insert(key, val)
+    }
+
+    pub fn delete(&mut self, key: &[u8]) -> Result<bool, BTreeError> {
+        self.tree.delete(key)
+    }
+
+    // 可以读到本次 Transaction 的修改
+    pub fn get_value(&self, key: &[u8]) -> Result<Option<Vec<u8>>, BTreeError> {
+        self.tree.get_value(key)
+    }
+
+    // 释放被替换的旧 page，并持久化新的根节点
+    pub fn commit(mut self) -> Result<(), BTreeError> {
+        self.done = true;
+        let txn = self.tree.txn.take().unwrap();
+        for ptr in txn.freed {
+            self.tree.del(ptr);
+        }
+        self.tree.commit()
+    }
+
+    pub fn rollback(mut self) {
+        self.abort();
+    }
+
+    // 释放本次分配的 page，恢复开始时的根节点
+    fn abort(&mut self) {
+        self.done = true;
+        let txn = self.tree.txn.take().unwrap();
+        for ptr in txn.allocated {
+            self.tree.del(ptr);
+        }
+        self.tree.set_root(self.root);
+    }
+}
+
+// 索引的修改与主树在同一个 Transaction 中提交或回滚
+impl Transaction<'_> {
+    pub fn insert_indexed(
+        &mut self,
+        key: &[u8],
+        val: &[u8],
+        indexes: &[Index],
+    ) -> Result<(), BTreeError> {
+        self.tree.insert_indexed(key, val, indexes)
+    }
+
+    pub fn delete_indexed(&mut self, key: &[u8], indexes: &[Index]) -> Result<bool, BTreeError> {
+        self.tree.delete_indexed(key, indexes)
+    }
+
+    pub fn index_scan(&self, name: &str, index_key: &[u8]) -> Result<Vec<Vec<u8>>, BTreeError> {
+        self.tree.index_scan(name, index_key)
+    }
+}
+
+impl<C: KeyComparator> Drop for Transaction<'_, C> {
+    fn drop(&mut self) {
+        if !self.done {
+            self.abort();
+        }
+    }
+}
+
+impl<C: KeyComparator> BTree<C> {
+    // 开始一个写事务，Transaction 结束之前不能直接访问 BTree
+    pub fn begin(&mut self) -> Transaction<'_, C> {
+        self.txn = Some(TxnState::default());
+        Transaction {
+            root: self.root_ptr(),
+            tree: self,
+            done: false,
+        }
+    }
+
+    // 在内部的事务中执行 f，出错时释放新分配的 page 并恢复开始时的根节点
+    // 已经在 Transaction 中时直接执行，由调用者决定是否回滚
+    pub(crate) fn atomic<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, BTreeError>,
+    ) -> Result<T, BTreeError> {
+        if self.txn.is_some() {
+            return f(self);
+        }
+        let root = self.root_ptr();
+        self.txn = Some(TxnState::default());
+        let result = f(self);
+        let txn = self.txn.take().unwrap();
+        let release = match result {
+            Ok(_) => txn.freed,
+            Err(_) => {
+                self.set_root(root);
+                txn.allocated.into_iter().collect()
+            }
+        };
+        for ptr in release {
+            self.del(ptr);
+        }
+        result
+    }
+}
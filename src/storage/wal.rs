@@ -0,0 +1,104 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Write},
+    path::Path,
+};
+
+use super::{checksum::crc32, error::BTreeError};
+
+const WAL_SIG: &[u8; 8] = b"BTREEWAL";
+
+// 日志记录
+// | sig | old root | new root | npages | free list | count | freed      | checksum |
+// | 8B  |    8B    |    8B    |   8B   |     8B    |   8B  | count * 8B |    4B    |
+const WAL_HEADER: usize = 48;
+
+// 一次提交的日志记录
+// 数据 page 写入文件并 fsync 之后才写日志，所以完整的记录可以直接重放
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalRecord {
+    pub old_root: u64,
+    pub new_root: u64,
+    pub npages: u64,
+    pub free_head: u64,
+    // 这次提交释放的 page，重放之后应当都在 free list 中
+    pub freed: Vec<u64>,
+}
+
+impl WalRecord {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(WAL_HEADER + 8 * self.freed.len() + 4);
+        data.extend_from_slice(WAL_SIG);
+        for n in [
+            self.old_root,
+            self.new_root,
+            self.npages,
+            self.free_head,
+            self.freed.len() as u64,
+        ] {
+            data.extend_from_slice(&n.to_le_bytes());
+        }
+        for ptr in &self.freed {
+            data.extend_from_slice(&ptr.to_le_bytes());
+        }
+        data.extend_from_slice(&crc32(&data).to_le_bytes());
+        data
+    }
+
+    // 不完整或者校验失败的记录返回 None，说明写日志时崩溃，这次提交没有生效
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() < WAL_HEADER + 4 || &data[..8] != WAL_SIG {
+            return None;
+        }
+        let field = |i: usize| u64::from_le_bytes(data[8 * i..8 * i + 8].try_into().unwrap());
+        let count = usize::try_from(field(5)).ok()?;
+        let len = count.checked_mul(8)?.checked_add(WAL_HEADER)?;
+        if data.len() < len + 4 {
+            return None;
+        }
+        let crc = u32::from_le_bytes(data[len..len + 4].try_into().unwrap());
+        if crc != crc32(&data[..len]) {
+            return None;
+        }
+
+        Some(WalRecord {
+            old_root: field(1),
+            new_root: field(2),
+            npages: field(3),
+            free_head: field(4),
+            freed: (0..count).map(|i| field(6 + i)).collect(),
+        })
+    }
+}
+
+// 读取日志中的记录，日志不存在或为空时返回 None
+pub fn read_wal(path: &Path) -> Result<Option<WalRecord>, BTreeError> {
+    let mut data = vec![];
+    match File::open(path) {
+        Ok(mut fp) => fp.read_to_end(&mut data)?,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    Ok(WalRecord::decode(&data))
+}
+
+// 写入记录并 fsync
+pub fn append_wal(path: &Path, record: &WalRecord) -> Result<(), BTreeError> {
+    let mut fp = OpenOptions::new().create(true).append(true).open(path)?;
+    fp.write_all(&record.encode())?;
+    fp.sync_all()?;
+    Ok(())
+}
+
+// master page 更新之后清空日志
+pub fn truncate_wal(path: &Path) -> Result<(), BTreeError> {
+    match OpenOptions::new().write(true).open(path) {
+        Ok(fp) => {
+            fp.set_len(0)?;
+            fp.sync_all()?;
+            Ok(())
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err.into()),
+    }
+}
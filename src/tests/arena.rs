@@ -0,0 +1,75 @@
+use super::{alloc_counter::count_allocations, util::new_tree};
+use crate::storage::{
+    arena::NodeArena,
+    b_tree::{BNode, BTree},
+};
+
+fn key(i: u32) -> Vec<u8> {
+    // 打乱插入顺序，让分裂发生在树的各个位置
+    format!("key{:08}", i.wrapping_mul(2_654_435_761) % 1_000_003).into_bytes()
+}
+
+// 预热之后插入 count 个 key，返回内存分配次数
+// 较大的 value 让树有三层，并且插入时经常分裂
+fn insert_allocations(tree: &mut BTree, count: u32) -> usize {
+    for i in 0..2000 {
+        tree.insert(&key(i), &[0; 400]).unwrap();
+    }
+    let keys: Vec<Vec<u8>> = (2000..2000 + count).map(key).collect();
+    let (_, allocations) = count_allocations(|| {
+        for k in &keys {
+            tree.insert(k, &[1; 400]).unwrap();
+        }
+    });
+    allocations
+}
+
+#[test]
+fn arena_reuses_buffers() {
+    let mut arena = NodeArena::new(2);
+    let mut node = arena.take(64);
+    node.data[0] = 1;
+    arena.put(node);
+    let node = arena.take(32);
+    assert_eq!(node.data, vec![0; 32]);
+    assert_eq!((arena.hits(), arena.misses()), (1, 1));
+
+    // 容量不够的 buffer 不会被取出
+    arena.put(node);
+    arena.take(128);
+    assert_eq!((arena.hits(), arena.misses()), (1, 2));
+
+    // 池满之后归还的 buffer 直接释放
+    arena.put(BNode::new(8));
+    arena.put(BNode::new(8));
+    arena.put(BNode::new(8));
+    arena.take(8);
+    arena.take(8);
+    arena.take(8);
+    assert_eq!((arena.hits(), arena.misses()), (3, 3));
+}
+
+#[test]
+fn inserts_after_warm_up_hit_the_arena() {
+    let mut tree = new_tree();
+    insert_allocations(&mut tree, 1000);
+    let (hits, misses) = (tree.arena.hits(), tree.arena.misses());
+    assert!(misses < 16, "misses: {misses}");
+    assert!(hits > 100 * misses, "hits: {hits}, misses: {misses}");
+}
+
+#[test]
+fn arena_cuts_insert_allocations() {
+    let mut pooled = new_tree();
+    let with_arena = insert_allocations(&mut pooled, 1000);
+
+    let mut unpooled = new_tree();
+    unpooled.arena = NodeArena::new(0);
+    let without_arena = insert_allocations(&mut unpooled, 1000);
+
+    // 每次插入至少少分配 3 个临时节点
+    assert!(
+        with_arena + 3 * 1000 < without_arena,
+        "with arena: {with_arena}, without: {without_arena}"
+    );
+}
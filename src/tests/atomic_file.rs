@@ -0,0 +1,82 @@
+use std::{fs, io, path::Path};
+
+use super::{
+    file_store::TempDb,
+    test::{save_data_2, save_data_3},
+};
+use crate::storage::atomic_file::{tmp_path, write_atomic};
+
+// 与 path 同一目录下遗留的临时文件
+fn stray_tmp_files(path: &Path) -> Vec<String> {
+    let name = path.file_name().unwrap().to_string_lossy().into_owned();
+    fs::read_dir(path.parent().unwrap())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|file| file.starts_with(&format!("{name}.tmp.")))
+        .collect()
+}
+
+#[test]
+fn tmp_path_is_deterministic() {
+    let path = Path::new("/tmp/some.db.master");
+    assert_eq!(tmp_path(path), tmp_path(path));
+    assert_eq!(
+        tmp_path(path).to_string_lossy(),
+        format!("/tmp/some.db.master.tmp.{}", std::process::id())
+    );
+}
+
+#[test]
+fn save_helpers_replace_the_file() {
+    let db = TempDb::new();
+    for save in [save_data_2, save_data_3] {
+        save(db.path.clone(), b"first").unwrap();
+        assert_eq!(fs::read(&db.path).unwrap(), b"first");
+        save(db.path.clone(), b"second").unwrap();
+        assert_eq!(fs::read(&db.path).unwrap(), b"second");
+        assert!(stray_tmp_files(&db.path).is_empty());
+    }
+}
+
+#[test]
+fn failed_write_keeps_original_and_removes_tmp() {
+    let db = TempDb::new();
+    save_data_3(db.path.clone(), b"original").unwrap();
+
+    // 写到一半出错
+    let err = write_atomic(&db.path, |fp| {
+        io::Write::write_all(fp, b"partial")?;
+        Err(io::Error::other("disk full"))
+    })
+    .unwrap_err();
+    assert_eq!(err.to_string(), "disk full");
+    assert_eq!(fs::read(&db.path).unwrap(), b"original");
+    assert!(stray_tmp_files(&db.path).is_empty());
+
+    // rename 失败：目标是一个非空目录
+    fs::remove_file(&db.path).unwrap();
+    fs::create_dir(&db.path).unwrap();
+    fs::write(db.path.join("keep"), b"x").unwrap();
+    for save in [save_data_2, save_data_3] {
+        assert!(save(db.path.clone(), b"new").is_err());
+        assert!(stray_tmp_files(&db.path).is_empty());
+    }
+    assert_eq!(fs::read(db.path.join("keep")).unwrap(), b"x");
+    fs::remove_dir_all(&db.path).unwrap();
+}
+
+#[test]
+fn file_store_commit_leaves_no_tmp_files() {
+    let db = TempDb::new();
+    let mut tree = db.open();
+    for i in 0..10_u8 {
+        tree.insert(&[b'k', i], b"v").unwrap();
+        tree.commit().unwrap();
+    }
+    drop(tree);
+
+    let master = Path::new(&format!("{}.master", db.path.to_string_lossy())).to_path_buf();
+    assert!(master.exists());
+    assert!(stray_tmp_files(&master).is_empty());
+    assert_eq!(db.open().len().unwrap(), 10);
+}
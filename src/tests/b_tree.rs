@@ -0,0 +1,429 @@
+use rand::seq::SliceRandom;
+
+use super::{
+    alloc_counter::count_allocations,
+    util::{key, new_tree},
+};
+use crate::storage::{
+    b_tree::{optimal_fanout, optimal_leaf_entries, BNode, NodeType, BTREE_PAGE_SIZE},
+    comparator::ByteOrder,
+    error::BTreeError,
+};
+
+#[test]
+fn optimal_fanout_matches_layout() {
+    // HEADER 包括 checksum，为 8 字节，每个 k-v 另有 ptr、offset 和长度共 14 字节
+    // (4096 - HEADER) / (14 + 8)
+    assert_eq!(optimal_fanout(4096, 8, 100), 185);
+    // (4096 - HEADER) / (14 + 8 + 100)
+    assert_eq!(optimal_leaf_entries(4096, 8, 100), 33);
+
+    // BTreeConfig::validate 允许的最大 k-v：(4096 - HEADER) / (14 + 1000 + 3000)
+    assert_eq!(optimal_leaf_entries(4096, 1000, 3000), 1);
+    assert_eq!(optimal_fanout(0, 8, 8), 0);
+}
+
+#[test]
+fn node_accessors_round_trip() {
+    let mut node = BNode::new(BTREE_PAGE_SIZE);
+    node.set_header(NodeType::Leaf as u16, 2);
+    node.node_append_kv(0, 11, b"a".to_vec(), b"x".to_vec())
+        .unwrap();
+    node.node_append_kv(1, 22, b"bb".to_vec(), b"yy".to_vec())
+        .unwrap();
+
+    assert_eq!(node.btype(), NodeType::Leaf as u16);
+    assert_eq!(node.nkeys(), 2);
+    assert_eq!(node.get_ptr(0), 11);
+    assert_eq!(node.get_ptr(1), 22);
+    assert_eq!(node.get_offset(0), 0);
+    assert_eq!(node.get_offset(1), 4 + 1 + 1);
+    assert_eq!(node.get_offset(2), 4 + 1 + 1 + 4 + 2 + 2);
+    assert_eq!(node.get_key(0), b"a");
+    assert_eq!(node.get_val(0), b"x");
+    assert_eq!(node.get_key(1), b"bb");
+    assert_eq!(node.get_val(1), b"yy");
+
+    node.set_ptr(1, u64::MAX);
+    assert_eq!(node.get_ptr(1), u64::MAX);
+    node.set_offset(2, 1234);
+    assert_eq!(node.get_offset(2), 1234);
+}
+
+fn big_leaf(n: u16, val_len: usize) -> BNode {
+    let mut node = BNode::new(2 * BTREE_PAGE_SIZE);
+    node.set_header(NodeType::Leaf as u16, n);
+    for i in 0..n {
+        let key = format!("key{:04}", i).into_bytes();
+        node.node_append_kv(i, 0, key, vec![i as u8; val_len])
+            .unwrap();
+    }
+    node
+}
+
+fn keys_of(nodes: &[BNode]) -> Vec<Vec<u8>> {
+    nodes
+        .iter()
+        .flat_map(|node| (0..node.nkeys()).map(|i| node.get_key(i)))
+        .collect()
+}
+
+#[test]
+fn node_split_3_two_way() {
+    let mut node = big_leaf(3, 1500);
+    let expected = keys_of(&[node.clone()]);
+
+    let (n, nodes) = node.node_split_3(BTREE_PAGE_SIZE);
+    assert_eq!(n, 2);
+    assert_eq!(nodes.len(), 2);
+    for node in &nodes {
+        assert!(node.n_bytes() as usize <= BTREE_PAGE_SIZE);
+    }
+    assert_eq!(keys_of(&nodes), expected);
+}
+
+#[test]
+fn node_split_3_three_way() {
+    let mut node = big_leaf(7, 1100);
+    let expected = keys_of(&[node.clone()]);
+
+    let (n, nodes) = node.node_split_3(BTREE_PAGE_SIZE);
+    assert_eq!(n, 3);
+    assert_eq!(nodes.len(), 3);
+    for node in &nodes {
+        assert!(node.n_bytes() as usize <= BTREE_PAGE_SIZE);
+        assert_eq!(node.btype(), NodeType::Leaf as u16);
+    }
+    assert_eq!(keys_of(&nodes), expected);
+    assert_eq!(nodes[0].get_val(0), vec![0; 1100]);
+    assert_eq!(nodes[2].get_val(nodes[2].nkeys() - 1), vec![6; 1100]);
+}
+
+#[test]
+fn node_split_2_balances_halves() {
+    let node = big_leaf(100, 60);
+    let mut left = BNode::new(2 * BTREE_PAGE_SIZE);
+    let mut right = BNode::new(BTREE_PAGE_SIZE);
+    node.node_split_2(&mut left, &mut right, BTREE_PAGE_SIZE);
+
+    assert_eq!((left.nkeys(), right.nkeys()), (50, 50));
+    assert!(left.n_bytes() as usize <= BTREE_PAGE_SIZE);
+    assert!(right.n_bytes() as usize <= BTREE_PAGE_SIZE);
+    assert_eq!(keys_of(&[left, right]), keys_of(&[node]));
+
+    // 一边的 key 较大时仍然保证两边都能装下
+    let mut node = BNode::new(2 * BTREE_PAGE_SIZE);
+    node.set_header(NodeType::Leaf as u16, 4);
+    for (i, len) in [100, 100, 3000, 3000].into_iter().enumerate() {
+        node.node_append_kv(i as u16, 0, vec![b'a' + i as u8], vec![0; len])
+            .unwrap();
+    }
+    let mut left = BNode::new(2 * BTREE_PAGE_SIZE);
+    let mut right = BNode::new(BTREE_PAGE_SIZE);
+    node.node_split_2(&mut left, &mut right, BTREE_PAGE_SIZE);
+    assert_eq!((left.nkeys(), right.nkeys()), (3, 1));
+    assert!(right.n_bytes() as usize <= BTREE_PAGE_SIZE);
+}
+
+#[test]
+fn sequential_inserts_keep_tree_shallow() {
+    let mut tree = new_tree();
+    for i in 0..10_000_u32 {
+        tree.insert(&key(i), &[0; 50]).unwrap();
+    }
+    let report = tree.check().unwrap();
+    assert_eq!(tree.len().unwrap(), 10_000);
+    // 分裂之后的叶子节点大约半满，而不是只剩下一个 key
+    assert!(report.height <= 3, "height: {}", report.height);
+}
+
+#[test]
+fn node_append_range_copies_middle_slice() {
+    let mut old = BNode::new(BTREE_PAGE_SIZE);
+    old.set_header(NodeType::Leaf as u16, 4);
+    for i in 0..4_u16 {
+        let key = format!("k{i}").into_bytes();
+        let val = vec![b'v'; i as usize + 1];
+        old.node_append_kv(i, 100 + i as u64, key, val).unwrap();
+    }
+
+    let mut new = BNode::new(BTREE_PAGE_SIZE);
+    new.set_header(NodeType::Leaf as u16, 3);
+    new.node_append_kv(0, 7, b"a".to_vec(), b"first".to_vec())
+        .unwrap();
+    new.node_append_range(&old, 1, 1, 2);
+
+    assert_eq!(new.get_key(0), b"a");
+    assert_eq!(new.get_val(0), b"first");
+    assert_eq!(new.get_ptr(0), 7);
+    for i in 1..3_u16 {
+        assert_eq!(new.get_key(i), old.get_key(i));
+        assert_eq!(new.get_val(i), old.get_val(i));
+        assert_eq!(new.get_ptr(i), old.get_ptr(i));
+    }
+    assert_eq!(
+        new.n_bytes() as usize,
+        new.kv_pos(1) + (old.kv_pos(3) - old.kv_pos(1))
+    );
+}
+
+#[test]
+fn invalid_node_type_is_an_error() {
+    let mut node = BNode::new(BTREE_PAGE_SIZE);
+    node.set_header(99, 1);
+    node.node_append_kv(0, 0, b"k".to_vec(), b"v".to_vec())
+        .unwrap();
+    assert_eq!(
+        node.node_type().err(),
+        Some(BTreeError::InvalidNodeType(99))
+    );
+
+    let mut tree = new_tree();
+    let ptr = tree.new(&node).unwrap();
+    assert_eq!(tree.get(ptr).err(), Some(BTreeError::InvalidNodeType(99)));
+    assert_eq!(tree.get(ptr + 1).err(), Some(BTreeError::CorruptPage));
+}
+
+// 线性查找的实现，作为二分查找的参照
+fn lookup_le_linear(node: &BNode, key: &[u8]) -> u16 {
+    let mut found = 0_u16;
+    for i in 1..node.nkeys() {
+        if node.get_key(i).as_slice() <= key {
+            found = i;
+        } else {
+            break;
+        }
+    }
+    found
+}
+
+#[test]
+fn node_lookup_le_matches_linear_scan() {
+    let mut node = BNode::new(BTREE_PAGE_SIZE);
+    node.set_header(NodeType::Leaf as u16, 200);
+    for i in 0..200_u16 {
+        // 偶数 key，这样奇数 probe 落在两个 key 之间
+        let key = format!("{:04}", 2 * i + 10).into_bytes();
+        node.node_append_kv(i, 0, key, vec![]).unwrap();
+    }
+    assert!(node.n_bytes() as usize <= BTREE_PAGE_SIZE);
+
+    for probe in 0..500 {
+        let key = format!("{:04}", probe).into_bytes();
+        assert_eq!(
+            node.node_lookup_le(&key),
+            lookup_le_linear(&node, &key),
+            "probe {probe}"
+        );
+    }
+    assert_eq!(node.node_lookup_le(b""), 0);
+    assert_eq!(node.node_lookup_le(b"0009"), 0);
+    assert_eq!(node.node_lookup_le(b"0010"), 0);
+    assert_eq!(node.node_lookup_le(b"0012"), 1);
+    assert_eq!(node.node_lookup_le(b"9999"), 199);
+
+    let mut single = BNode::new(BTREE_PAGE_SIZE);
+    single.set_header(NodeType::Leaf as u16, 1);
+    single.node_append_kv(0, 0, b"k".to_vec(), vec![]).unwrap();
+    assert_eq!(single.node_lookup_le(b"a"), 0);
+    assert_eq!(single.node_lookup_le(b"z"), 0);
+}
+
+#[test]
+fn node_type_try_from() {
+    assert_eq!(NodeType::try_from(1), Ok(NodeType::Node));
+    assert_eq!(NodeType::try_from(2), Ok(NodeType::Leaf));
+    assert_eq!(NodeType::try_from(0), Err(BTreeError::InvalidNodeType(0)));
+    assert_eq!(NodeType::try_from(3), Err(BTreeError::InvalidNodeType(3)));
+}
+
+#[test]
+fn node_lookup_le_does_not_allocate() {
+    let mut node = BNode::new(BTREE_PAGE_SIZE);
+    node.set_header(NodeType::Leaf as u16, 100);
+    for i in 0..100_u16 {
+        node.node_append_kv(i, 0, format!("key{:04}", i).into_bytes(), vec![b'v'; 8])
+            .unwrap();
+    }
+    let probes: Vec<Vec<u8>> = (0..100)
+        .map(|i| format!("key{:04}", i).into_bytes())
+        .collect();
+
+    let (found, allocations) = count_allocations(|| {
+        probes
+            .iter()
+            .map(|probe| node.node_lookup_le(probe) as usize)
+            .sum::<usize>()
+    });
+    assert_eq!(found, (0..100).sum::<usize>());
+    assert_eq!(allocations, 0);
+
+    let (_, allocations) = count_allocations(|| node.get_key(3));
+    assert_eq!(allocations, 1);
+    assert_eq!(node.get_key_ref(3), node.get_key(3).as_slice());
+    assert_eq!(node.get_val_ref(3), node.get_val(3).as_slice());
+}
+
+fn leaf_of(keys: &[&[u8]]) -> BNode {
+    let mut node = BNode::new(BTREE_PAGE_SIZE);
+    node.set_header(NodeType::Leaf as u16, keys.len() as u16);
+    for (i, key) in keys.iter().enumerate() {
+        node.node_append_kv(i as u16, 0, key.to_vec(), b"v".to_vec())
+            .unwrap();
+    }
+    node
+}
+
+#[test]
+fn check_key_order_rejects_duplicates() {
+    let old = leaf_of(&[b"", b"a", b"b"]);
+    assert_eq!(old.check_key_order(&ByteOrder), Ok(()));
+
+    // 本应更新 idx 2，错误地插入到了后面
+    let mut new = BNode::new(BTREE_PAGE_SIZE);
+    new.leaf_insert(&old, 3, b"b".to_vec(), b"w".to_vec())
+        .unwrap();
+    assert_eq!(
+        new.check_key_order(&ByteOrder),
+        Err(BTreeError::CorruptPage)
+    );
+
+    let mut new = BNode::new(BTREE_PAGE_SIZE);
+    new.leaf_insert(&old, 1, b"c".to_vec(), b"w".to_vec())
+        .unwrap();
+    assert_eq!(
+        new.check_key_order(&ByteOrder),
+        Err(BTreeError::CorruptPage)
+    );
+}
+
+// 只有 debug 构建在插入时检查
+#[cfg(debug_assertions)]
+#[test]
+fn insert_into_unordered_leaf_is_rejected() {
+    let mut tree = new_tree();
+    let ptr = tree.new(&leaf_of(&[b"", b"a", b"c", b"b"])).unwrap();
+    tree.set_root(ptr);
+
+    // 二分查找在乱序的节点中定位到 "a" 之后，插入之后出现两个 "b"
+    assert_eq!(tree.insert(b"b", b"x"), Err(BTreeError::CorruptPage));
+    assert_eq!(tree.root_ptr(), ptr);
+}
+
+#[test]
+fn ordered_inserts_pass_key_order_guard() {
+    let mut tree = new_tree();
+    let mut keys: Vec<u32> = (0..3000).collect();
+    keys.shuffle(&mut rand::thread_rng());
+    for i in keys {
+        tree.insert(format!("key{i:05}").as_bytes(), b"value")
+            .unwrap();
+        tree.insert(format!("key{i:05}").as_bytes(), b"updated")
+            .unwrap();
+    }
+    tree.check().unwrap();
+    assert_eq!(tree.len().unwrap(), 3000);
+}
+
+#[test]
+fn raw_round_trip_and_describe() {
+    let mut node = leaf_of(&[b"", b"apple", b"banana"]);
+    node.data.truncate(BTREE_PAGE_SIZE);
+    let copy = BNode::from_bytes(node.raw().to_vec());
+    assert_eq!(copy.raw(), node.raw());
+    assert_eq!(keys_of(std::slice::from_ref(&copy)), keys_of(&[node]));
+
+    let text = copy.describe();
+    assert!(text.starts_with("type=Leaf nkeys=3 bytes="), "{text}");
+    // "apple" 的十六进制
+    assert!(text.contains("key=6170706c65 len=5"), "{text}");
+
+    let mut internal = BNode::new(BTREE_PAGE_SIZE);
+    internal.set_header(NodeType::Node as u16, 1);
+    internal
+        .node_append_kv(0, 42, vec![0xab; 20], vec![])
+        .unwrap();
+    let text = internal.describe();
+    assert!(text.starts_with("type=Node nkeys=1"), "{text}");
+    assert!(text.contains(&format!("key={}.. len=20 ptr=42", "ab".repeat(16))));
+
+    // 无法识别的 page 只描述头部
+    let text = BNode::from_bytes(vec![0xff; 64]).describe();
+    assert_eq!(text, "type=Unknown(65535) nkeys=65535");
+}
+
+#[test]
+fn node_append_kv_rejects_lengths_that_do_not_fit() {
+    let mut node = BNode::new(4 * BTREE_PAGE_SIZE * 8);
+    node.set_header(NodeType::Leaf as u16, 2);
+    assert_eq!(
+        node.node_append_kv(0, 0, b"k".to_vec(), vec![0; 70_000]),
+        Err(BTreeError::ValueTooLong)
+    );
+    assert_eq!(
+        node.node_append_kv(0, 0, vec![0; 70_000], b"v".to_vec()),
+        Err(BTreeError::KeyTooLong)
+    );
+    // value 的最高位是 overflow 标记
+    assert_eq!(
+        node.node_append_kv(0, 0, b"k".to_vec(), vec![0; 1 << 15]),
+        Err(BTreeError::ValueTooLong)
+    );
+
+    // 两个 32 KB 的 value 之后 offset 超出 u16
+    let val = vec![0; (1 << 15) - 1];
+    node.node_append_kv(0, 0, b"k".to_vec(), val.clone())
+        .unwrap();
+    assert_eq!(
+        node.node_append_kv(1, 0, b"kk".to_vec(), val),
+        Err(BTreeError::CorruptPage)
+    );
+
+    // 写不下的 k-v 不会越界
+    let mut small = BNode::new(64);
+    small.set_header(NodeType::Leaf as u16, 1);
+    assert_eq!(
+        small.node_append_kv(0, 0, b"k".to_vec(), vec![0; 64]),
+        Err(BTreeError::PageTooLarge)
+    );
+}
+
+#[test]
+fn node_filled_to_page_boundary_has_exact_offsets() {
+    // HEADER + 4 * (8 + 2 + 4 + 4 + val) == BTREE_PAGE_SIZE
+    let val_len = (BTREE_PAGE_SIZE - 8) / 4 - 18;
+    let mut node = BNode::new(BTREE_PAGE_SIZE);
+    node.set_header(NodeType::Leaf as u16, 4);
+    for i in 0..4_u16 {
+        let key = format!("k{i:03}").into_bytes();
+        node.node_append_kv(i, 0, key, vec![i as u8; val_len])
+            .unwrap();
+    }
+    assert_eq!(node.n_bytes() as usize, BTREE_PAGE_SIZE);
+    for i in 0..=4_u16 {
+        assert_eq!(node.get_offset(i) as usize, i as usize * (8 + val_len));
+    }
+    assert_eq!(node.get_val(3), vec![3; val_len]);
+
+    // 再多一个字节就放不下了
+    let mut node = BNode::new(BTREE_PAGE_SIZE);
+    node.set_header(NodeType::Leaf as u16, 4);
+    for i in 0..3_u16 {
+        let key = format!("k{i:03}").into_bytes();
+        node.node_append_kv(i, 0, key, vec![0; val_len]).unwrap();
+    }
+    assert_eq!(
+        node.node_append_kv(3, 0, b"k003".to_vec(), vec![0; val_len + 1]),
+        Err(BTreeError::PageTooLarge)
+    );
+}
+
+#[test]
+fn huge_value_is_rejected_or_stored_cleanly() {
+    let mut tree = new_tree();
+    // 超出 max_val_size 的 value 写入 overflow page，而不是截断长度
+    let val = vec![7; 70_000];
+    tree.insert(b"big", &val).unwrap();
+    assert_eq!(tree.get_value(b"big").unwrap(), Some(val));
+    tree.check().unwrap();
+}
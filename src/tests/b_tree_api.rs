@@ -0,0 +1,411 @@
+use std::ops::Bound;
+
+use super::{
+    shared_store::SharedStore,
+    util::{key, new_tree},
+};
+use crate::storage::{
+    b_tree::{
+        BTree, NodeType, BTREE_MAX_BLOB_SIZE, BTREE_MAX_KEY_SIZE, BTREE_MAX_VAL_SIZE,
+        BTREE_PAGE_SIZE,
+    },
+    codec::RunLength,
+    error::BTreeError,
+};
+
+fn val(i: u32) -> Vec<u8> {
+    format!("val{:06}", i).repeat(8).into_bytes()
+}
+
+#[test]
+fn insert_sequential_and_get() {
+    let mut tree = new_tree();
+    assert_eq!(tree.get_value(&key(0)).unwrap(), None);
+
+    for i in 0..1000 {
+        tree.insert(&key(i), &val(i)).unwrap();
+    }
+    for i in 0..1000 {
+        assert_eq!(tree.get_value(&key(i)).unwrap(), Some(val(i)));
+    }
+    assert_eq!(tree.get_value(b"key9999999").unwrap(), None);
+    assert_eq!(tree.get_value(b"a").unwrap(), None);
+}
+
+#[test]
+fn delete_makes_key_absent() {
+    let mut tree = new_tree();
+    for i in 0..1000 {
+        tree.insert(&key(i), &val(i)).unwrap();
+    }
+
+    assert!(tree.delete(&key(500)).unwrap());
+    assert_eq!(tree.get_value(&key(500)).unwrap(), None);
+    assert!(!tree.delete(&key(500)).unwrap());
+    assert_eq!(tree.get_value(&key(499)).unwrap(), Some(val(499)));
+    assert_eq!(tree.get_value(&key(501)).unwrap(), Some(val(501)));
+
+    for i in 0..1000 {
+        tree.delete(&key(i)).unwrap();
+    }
+    for i in 0..1000 {
+        assert_eq!(tree.get_value(&key(i)).unwrap(), None);
+    }
+    tree.insert(&key(1), &val(1)).unwrap();
+    assert_eq!(tree.get_value(&key(1)).unwrap(), Some(val(1)));
+}
+
+#[test]
+fn insert_enforces_size_limits() {
+    let mut tree = new_tree();
+    for i in 0..100 {
+        tree.insert(&key(i), &val(i)).unwrap();
+    }
+    let root = tree.root_ptr();
+
+    let max_key = vec![b'k'; BTREE_MAX_KEY_SIZE];
+    let max_val = vec![b'v'; BTREE_MAX_VAL_SIZE];
+    tree.insert(&max_key, &max_val).unwrap();
+    assert_eq!(tree.get_value(&max_key).unwrap(), Some(max_val));
+    assert_ne!(tree.root_ptr(), root);
+    let root = tree.root_ptr();
+
+    let long_key = vec![b'k'; BTREE_MAX_KEY_SIZE + 1];
+    assert_eq!(tree.insert(&long_key, b"v"), Err(BTreeError::KeyTooLong));
+    let long_val = vec![b'v'; BTREE_MAX_BLOB_SIZE + 1];
+    assert_eq!(
+        tree.insert(b"key", &long_val),
+        Err(BTreeError::ValueTooLong)
+    );
+
+    // 被拒绝的插入不会修改树
+    assert_eq!(tree.root_ptr(), root);
+    assert_eq!(tree.get_value(&long_key).unwrap(), None);
+    assert_eq!(tree.get_value(b"key").unwrap(), None);
+    for i in 0..100 {
+        assert_eq!(tree.get_value(&key(i)).unwrap(), Some(val(i)));
+    }
+}
+
+#[test]
+fn failed_insert_keeps_old_pages() {
+    let store = SharedStore::default();
+    let mut tree = BTree::with_store(Box::new(store.clone()));
+    for i in 0..2000 {
+        tree.insert(&key(i), &val(i)).unwrap();
+    }
+    tree.insert(b"big", &vec![7; 20_000]).unwrap();
+    let pages = store.len();
+
+    // 分配新 page 失败时，旧的路径和被覆盖的 overflow page 都不能被释放
+    store.set_full(true);
+    let full = Err(BTreeError::Io(std::io::ErrorKind::StorageFull));
+    assert_eq!(tree.insert(&key(5000), &val(5000)), full);
+    assert_eq!(tree.insert(b"big", b"small"), full);
+    store.set_full(false);
+
+    assert_eq!(store.len(), pages);
+    tree.check().unwrap();
+    assert_eq!(tree.get_value(b"big").unwrap(), Some(vec![7; 20_000]));
+    for i in 0..2000 {
+        assert_eq!(tree.get_value(&key(i)).unwrap(), Some(val(i)));
+    }
+}
+
+#[test]
+fn insert_descending_keys() {
+    let mut tree = new_tree();
+    for i in (1..=10).rev() {
+        tree.insert(format!("{:02}", i).as_bytes(), &val(i))
+            .unwrap();
+    }
+
+    let keys: Vec<_> = tree
+        .scan(Bound::Unbounded, Bound::Unbounded)
+        .map(|(k, _)| k)
+        .collect();
+    let expected: Vec<_> = (1..=10).map(|i| format!("{:02}", i).into_bytes()).collect();
+    assert_eq!(keys, expected);
+
+    // 更新已有的 key 不会插入新的 key
+    tree.insert(b"01", b"first").unwrap();
+    tree.insert(b"05", b"middle").unwrap();
+    tree.insert(b"10", b"last").unwrap();
+    let items: Vec<_> = tree.scan(Bound::Unbounded, Bound::Unbounded).collect();
+    assert_eq!(items.len(), 10);
+    assert_eq!(items[0], (b"01".to_vec(), b"first".to_vec()));
+    assert_eq!(items[4], (b"05".to_vec(), b"middle".to_vec()));
+    assert_eq!(items[9], (b"10".to_vec(), b"last".to_vec()));
+    assert_eq!(items[1], (b"02".to_vec(), val(2)));
+}
+
+#[test]
+fn insert_descending_keys_multi_level() {
+    let mut tree = new_tree();
+    for i in (0..1000).rev() {
+        tree.insert(&key(i), &val(i)).unwrap();
+    }
+
+    let items: Vec<_> = tree.scan(Bound::Unbounded, Bound::Unbounded).collect();
+    let expected: Vec<_> = (0..1000).map(|i| (key(i), val(i))).collect();
+    assert_eq!(items, expected);
+}
+
+#[test]
+fn contains_key_agrees_with_get_value() {
+    let mut tree = new_tree();
+    assert!(!tree.contains_key(b"").unwrap());
+    assert!(!tree.contains_key(&key(0)).unwrap());
+
+    // 只插入偶数 key
+    for i in (10..1000).step_by(2) {
+        tree.insert(&key(i), &val(i)).unwrap();
+    }
+
+    let mut probes: Vec<Vec<u8>> = (0..1010).map(key).collect();
+    // 比所有 key 都小或者都大的边界
+    probes.extend([b"".to_vec(), b"a".to_vec(), b"key".to_vec(), b"z".to_vec()]);
+    for probe in &probes {
+        let expected = tree.get_value(probe).unwrap().is_some();
+        assert_eq!(tree.contains_key(probe).unwrap(), expected, "{probe:?}");
+    }
+    assert!(tree.contains_key(&key(10)).unwrap());
+    assert!(!tree.contains_key(&key(9)).unwrap());
+    assert!(tree.contains_key(&key(998)).unwrap());
+    assert!(!tree.contains_key(&key(999)).unwrap());
+}
+
+#[test]
+fn upsert_counter() {
+    let mut tree = new_tree();
+    for i in 0..100 {
+        tree.insert(&key(i), &val(i)).unwrap();
+    }
+
+    let add = |old: &[u8]| {
+        let n = u64::from_le_bytes(old.try_into().unwrap());
+        (n + 1).to_le_bytes().to_vec()
+    };
+    for _ in 0..1000 {
+        tree.upsert(b"counter", &1_u64.to_le_bytes(), add).unwrap();
+    }
+    assert_eq!(
+        tree.get_value(b"counter").unwrap(),
+        Some(1000_u64.to_le_bytes().to_vec())
+    );
+    for i in 0..100 {
+        assert_eq!(tree.get_value(&key(i)).unwrap(), Some(val(i)));
+    }
+}
+
+#[test]
+fn upsert_rejects_oversized_merge() {
+    let mut tree = new_tree();
+    tree.upsert(b"key", b"init", |_| unreachable!()).unwrap();
+    assert_eq!(tree.get_value(b"key").unwrap(), Some(b"init".to_vec()));
+
+    // 超过 max_val_size 的结果保存在 overflow page 中
+    let grow = |old: &[u8]| old.repeat(1000);
+    tree.upsert(b"key", b"", grow).unwrap();
+    assert_eq!(tree.get_value(b"key").unwrap(), Some(b"init".repeat(1000)));
+
+    let root = tree.root_ptr();
+    let too_big = |_: &[u8]| vec![0; BTREE_MAX_BLOB_SIZE + 1];
+    assert_eq!(
+        tree.upsert(b"key", b"", too_big),
+        Err(BTreeError::ValueTooLong)
+    );
+    assert_eq!(tree.root_ptr(), root);
+    assert_eq!(tree.get_value(b"key").unwrap(), Some(b"init".repeat(1000)));
+}
+
+#[test]
+fn root_starts_with_empty_sentinel() {
+    let mut tree = new_tree();
+    tree.insert(b"m", b"1").unwrap();
+
+    let root = tree.get(tree.root_ptr()).unwrap();
+    assert_eq!(root.nkeys(), 2);
+    assert_eq!(root.get_key(0), b"");
+    assert_eq!(root.get_key(1), b"m");
+
+    // 比所有 key 都小的 key
+    assert_eq!(tree.get_value(b"a").unwrap(), None);
+    assert_eq!(tree.get_value(b"").unwrap(), None);
+    assert!(!tree.contains_key(b"").unwrap());
+    assert_eq!(tree.insert(b"", b"v"), Err(BTreeError::EmptyKey));
+    assert!(!tree.delete(b"").unwrap());
+
+    tree.insert(b"a", b"2").unwrap();
+    let items: Vec<_> = tree.iter().collect();
+    assert_eq!(
+        items,
+        vec![
+            (b"a".to_vec(), b"2".to_vec()),
+            (b"m".to_vec(), b"1".to_vec())
+        ]
+    );
+    assert_eq!(tree.iter_rev().count(), 2);
+    assert_eq!(tree.len().unwrap(), 2);
+    assert_eq!(tree.min().unwrap(), Some((b"a".to_vec(), b"2".to_vec())));
+
+    // 哨兵在分裂之后仍然是最左边叶子节点的第一个 key
+    for i in 0..1000 {
+        tree.insert(&key(i), &val(i)).unwrap();
+    }
+    let mut node = tree.get(tree.root_ptr()).unwrap();
+    loop {
+        assert_eq!(node.get_key(0), b"");
+        if node.node_type().unwrap() == NodeType::Leaf {
+            break;
+        }
+        node = tree.get(node.get_ptr(0)).unwrap();
+    }
+    assert_eq!(tree.iter().count(), 1002);
+    assert_eq!(tree.len().unwrap(), 1002);
+
+    // 删除所有 key 之后回到空树
+    for (key, _) in tree.iter().collect::<Vec<_>>() {
+        assert!(tree.delete(&key).unwrap());
+    }
+    assert_eq!(tree.root_ptr(), 0);
+}
+
+#[test]
+fn root_split_creates_internal_root() {
+    let mut tree = new_tree();
+    tree.insert(&key(0), &val(0)).unwrap();
+    let mut i = 1;
+    // 插入直到根节点第一次分裂
+    while tree.get(tree.root_ptr()).unwrap().btype() == NodeType::Leaf as u16 {
+        tree.insert(&key(i), &val(i)).unwrap();
+        i += 1;
+    }
+
+    let root = tree.get(tree.root_ptr()).unwrap();
+    assert_eq!(root.node_type(), Ok(NodeType::Node));
+    assert!((2..=3).contains(&root.nkeys()));
+    assert_eq!(root.raw().len(), BTREE_PAGE_SIZE);
+
+    let mut keys = vec![];
+    for idx in 0..root.nkeys() {
+        let kid = tree.get(root.get_ptr(idx)).unwrap();
+        assert_eq!(kid.node_type(), Ok(NodeType::Leaf));
+        // 每个子节点正好是一个 page，并且内部节点中的 key 是它的第一个 key
+        assert_eq!(kid.raw().len(), BTREE_PAGE_SIZE);
+        assert!(kid.n_bytes() as usize <= BTREE_PAGE_SIZE);
+        assert_eq!(root.get_key(idx), kid.get_key(0));
+        keys.extend((0..kid.nkeys()).map(|j| kid.get_key(j)));
+    }
+
+    // 子节点合起来就是分裂前的全部 key
+    let mut expected = vec![vec![]];
+    expected.extend((0..i).map(key));
+    assert_eq!(keys, expected);
+    tree.check().unwrap();
+}
+
+#[test]
+fn insert_into_non_root_leaf_of_two_level_tree() {
+    let mut tree = new_tree();
+    let mut i = 0;
+    while tree.root_ptr() == 0
+        || tree.get(tree.root_ptr()).unwrap().btype() == NodeType::Leaf as u16
+    {
+        tree.insert(&key(2 * i), &val(i)).unwrap();
+        i += 1;
+    }
+    let root = tree.get(tree.root_ptr()).unwrap();
+    let last = root.get_ptr(root.nkeys() - 1);
+
+    // 插入到最后一个叶子节点中
+    let new_key = key(2 * (i - 1) - 1);
+    assert_eq!(tree.get_value(&new_key).unwrap(), None);
+    tree.insert(&new_key, b"inserted").unwrap();
+    assert_eq!(
+        tree.get_value(&new_key).unwrap(),
+        Some(b"inserted".to_vec())
+    );
+
+    let root = tree.get(tree.root_ptr()).unwrap();
+    assert_eq!(root.node_type(), Ok(NodeType::Node));
+    let leaf = tree.get(root.get_ptr(root.nkeys() - 1)).unwrap();
+    assert_ne!(root.get_ptr(root.nkeys() - 1), last);
+    assert!((0..leaf.nkeys()).any(|j| leaf.get_key(j) == new_key));
+    for j in 0..i {
+        assert_eq!(tree.get_value(&key(2 * j)).unwrap(), Some(val(j)));
+    }
+    tree.check().unwrap();
+}
+
+#[test]
+fn merge_from_takes_union() {
+    // self 有 [0, 500)，other 有 [400, 900)，重叠 100 个 key
+    let mut tree = new_tree();
+    for i in 0..500 {
+        tree.insert(&key(i), &val(i)).unwrap();
+    }
+    let mut other = new_tree();
+    for i in 400..900 {
+        other.insert(&key(i), &other_val(i)).unwrap();
+    }
+
+    tree.merge_from(&other, |existing, incoming| {
+        let mut merged = existing.to_vec();
+        merged.extend_from_slice(b"+");
+        merged.extend_from_slice(incoming);
+        merged
+    })
+    .unwrap();
+
+    assert_eq!(tree.len().unwrap(), 900);
+    for i in 0..900 {
+        let expected = match i {
+            0..400 => val(i),
+            400..500 => [val(i), b"+".to_vec(), other_val(i)].concat(),
+            _ => other_val(i),
+        };
+        assert_eq!(tree.get_value(&key(i)).unwrap(), Some(expected));
+    }
+    tree.check().unwrap();
+
+    // other 不变
+    assert_eq!(other.len().unwrap(), 500);
+    assert_eq!(other.get_value(&key(0)).unwrap(), None);
+}
+
+fn other_val(i: u32) -> Vec<u8> {
+    format!("other{i}").into_bytes()
+}
+
+#[test]
+fn empty_value_is_distinct_from_missing_key() {
+    let plain = new_tree();
+    let compressed = new_tree().with_codec(Box::new(RunLength));
+    for mut tree in [plain, compressed] {
+        tree.insert(b"k", b"").unwrap();
+        assert_eq!(tree.get_value(b"k").unwrap(), Some(vec![]));
+        assert_eq!(tree.get_value(b"j").unwrap(), None);
+        assert_eq!(tree.get_value(b"l").unwrap(), None);
+        assert!(tree.contains_key(b"k").unwrap());
+        assert_eq!(tree.len().unwrap(), 1);
+        assert_eq!(tree.iter().collect::<Vec<_>>(), [(b"k".to_vec(), vec![])]);
+
+        // 在多层的树中更新为空 value，再改回非空
+        for i in 0..500 {
+            tree.insert(&key(i), if i % 2 == 0 { b"" } else { b"x" })
+                .unwrap();
+        }
+        tree.insert(&key(1), b"").unwrap();
+        tree.insert(&key(2), b"y").unwrap();
+        assert_eq!(tree.get_value(&key(0)).unwrap(), Some(vec![]));
+        assert_eq!(tree.get_value(&key(1)).unwrap(), Some(vec![]));
+        assert_eq!(tree.get_value(&key(2)).unwrap(), Some(b"y".to_vec()));
+        assert_eq!(tree.get_value(&key(500)).unwrap(), None);
+
+        assert!(tree.delete(b"k").unwrap());
+        assert_eq!(tree.get_value(b"k").unwrap(), None);
+        assert!(!tree.delete(b"k").unwrap());
+        tree.check().unwrap();
+    }
+}
@@ -0,0 +1,189 @@
+use super::{shared_store::SharedStore, util::new_tree};
+use rand::seq::SliceRandom;
+
+use crate::storage::b_tree::{BTree, NodeType};
+
+fn key(i: u32) -> Vec<u8> {
+    format!("{:0>200}", i).into_bytes()
+}
+
+fn val(i: u32) -> Vec<u8> {
+    vec![(i % 251) as u8; 300]
+}
+
+// 检查子树的结构，返回叶子节点的深度
+fn check_node(tree: &BTree, ptr: u64, depth: usize, leaf_depth: &mut Option<usize>) {
+    let node = tree.get(ptr).unwrap();
+    assert!(node.nkeys() > 0);
+    assert!(node.n_bytes() as usize <= tree.config().page_size);
+    for i in 1..node.nkeys() {
+        assert!(node.get_key(i - 1) < node.get_key(i));
+    }
+
+    match node.node_type().unwrap() {
+        NodeType::Leaf => match leaf_depth {
+            Some(d) => assert_eq!(*d, depth),
+            None => *leaf_depth = Some(depth),
+        },
+        NodeType::Node => {
+            for i in 0..node.nkeys() {
+                let kid = tree.get(node.get_ptr(i)).unwrap();
+                assert_eq!(node.get_key(i), kid.get_key(0));
+                check_node(tree, node.get_ptr(i), depth + 1, leaf_depth);
+            }
+        }
+    }
+}
+
+pub(super) fn check_tree(tree: &BTree) {
+    if tree.root_ptr() != 0 {
+        check_node(tree, tree.root_ptr(), 0, &mut None);
+    }
+}
+
+#[test]
+fn delete_half_in_random_order() {
+    let mut tree = new_tree();
+    for i in 0..500 {
+        tree.insert(&key(i), &val(i)).unwrap();
+    }
+    check_tree(&tree);
+    assert_eq!(
+        tree.get(tree.root_ptr()).unwrap().btype(),
+        NodeType::Node as u16
+    );
+
+    let mut keys: Vec<u32> = (0..500).collect();
+    keys.shuffle(&mut rand::thread_rng());
+    let (deleted, kept) = keys.split_at(250);
+
+    for (n, i) in deleted.iter().enumerate() {
+        assert!(tree.delete(&key(*i)).unwrap());
+        if n % 25 == 0 {
+            check_tree(&tree);
+        }
+    }
+    check_tree(&tree);
+
+    for i in deleted {
+        assert_eq!(tree.get_value(&key(*i)).unwrap(), None);
+    }
+    for i in kept {
+        assert_eq!(tree.get_value(&key(*i)).unwrap(), Some(val(*i)));
+    }
+
+    for i in kept {
+        assert!(tree.delete(&key(*i)).unwrap());
+    }
+    assert_eq!(tree.root_ptr(), 0);
+}
+
+#[test]
+fn delete_merges_underflowing_leaves() {
+    let mut tree = new_tree();
+    for i in 0..500 {
+        tree.insert(&key(i), &val(i)).unwrap();
+    }
+    let leaves_before = count_leaves(&tree, tree.root_ptr());
+    for i in 0..400 {
+        tree.delete(&key(i)).unwrap();
+    }
+    check_tree(&tree);
+
+    // 100 个 k-v 约 50 KB，合并后叶子节点的数量应当明显减少
+    let leaves_after = count_leaves(&tree, tree.root_ptr());
+    assert!(
+        leaves_after * 2 < leaves_before,
+        "{leaves_after} vs {leaves_before}"
+    );
+}
+
+fn count_leaves(tree: &BTree, ptr: u64) -> usize {
+    let node = tree.get(ptr).unwrap();
+    match node.node_type().unwrap() {
+        NodeType::Leaf => 1,
+        NodeType::Node => (0..node.nkeys())
+            .map(|i| count_leaves(tree, node.get_ptr(i)))
+            .sum(),
+    }
+}
+
+#[test]
+fn clear_frees_every_page() {
+    let store = SharedStore::default();
+    let mut tree = BTree::with_store(Box::new(store.clone()));
+    for i in 0..1000 {
+        tree.insert(&key(i), &val(i)).unwrap();
+    }
+    tree.insert(b"big", &vec![7; 20_000]).unwrap();
+    assert!(store.len() > 0);
+
+    tree.clear().unwrap();
+    assert_eq!(tree.root_ptr(), 0);
+    assert_eq!(tree.len().unwrap(), 0);
+    assert_eq!(tree.iter().count(), 0);
+    assert_eq!(store.len(), 0);
+
+    // 清空之后可以继续使用
+    tree.insert(&key(1), &val(1)).unwrap();
+    assert_eq!(tree.get_value(&key(1)).unwrap(), Some(val(1)));
+    tree.clear().unwrap();
+    tree.clear().unwrap();
+    assert_eq!(store.len(), 0);
+}
+
+#[test]
+fn root_collapses_as_tree_shrinks() {
+    let store = SharedStore::default();
+    let mut tree = BTree::with_store(Box::new(store.clone()));
+    for i in 0..500 {
+        tree.insert(&key(i), &val(i)).unwrap();
+    }
+    let mut height = tree.check().unwrap().height;
+    assert!(height >= 3, "height: {height}");
+
+    let mut keys: Vec<u32> = (0..500).collect();
+    keys.shuffle(&mut rand::thread_rng());
+    for (n, i) in keys.iter().enumerate() {
+        assert!(tree.delete(&key(*i)).unwrap());
+        if tree.root_ptr() == 0 {
+            assert_eq!(n, 499);
+            break;
+        }
+
+        // 根节点要么是叶子节点，要么至少有两个子节点
+        let root = tree.get(tree.root_ptr()).unwrap();
+        if root.node_type().unwrap() == NodeType::Node {
+            assert!(root.nkeys() >= 2);
+        }
+        // 被移除的根节点都已经释放
+        let report = tree.check().unwrap();
+        assert_eq!(store.len(), report.nodes);
+        assert!(report.height <= height);
+        height = report.height;
+    }
+    assert_eq!(height, 1);
+    assert_eq!(store.len(), 0);
+}
+
+#[test]
+fn delete_to_one_key_leaves_single_leaf() {
+    let store = SharedStore::default();
+    let mut tree = BTree::with_store(Box::new(store.clone()));
+    for i in 0..200 {
+        tree.insert(&key(i), &val(i)).unwrap();
+    }
+    assert!(tree.check().unwrap().height > 1);
+
+    // 从两端删除，合并发生在树的左右两侧
+    for i in (0..100).chain(101..200) {
+        assert!(tree.delete(&key(i)).unwrap());
+    }
+    let root = tree.get(tree.root_ptr()).unwrap();
+    assert_eq!(root.node_type().unwrap(), NodeType::Leaf);
+    // 哨兵和剩下的一个 key
+    assert_eq!(root.nkeys(), 2);
+    assert_eq!(store.len(), 1);
+    assert_eq!(tree.get_value(&key(100)).unwrap(), Some(val(100)));
+    assert_eq!(tree.iter().count(), 1);
+}
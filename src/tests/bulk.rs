@@ -0,0 +1,117 @@
+use super::{
+    shared_store::SharedStore,
+    util::{key, new_tree},
+};
+use crate::storage::{
+    b_tree::{BTree, NodeType},
+    error::BTreeError,
+};
+
+fn val(i: u32) -> Vec<u8> {
+    format!("val{:06}", i)
+        .repeat(i as usize % 8 + 1)
+        .into_bytes()
+}
+
+fn items(n: u32) -> impl Iterator<Item = (Vec<u8>, Vec<u8>)> {
+    (0..n).map(|i| (key(i), val(i)))
+}
+
+#[test]
+fn bulk_load_sorted_keys() {
+    let store = SharedStore::default();
+    let mut tree = BTree::with_store(Box::new(store.clone()));
+    tree.bulk_load(items(10_000)).unwrap();
+
+    let report = tree.check().unwrap();
+    assert!(report.height >= 2);
+    assert_eq!(report.nodes, store.len());
+    assert_eq!(
+        tree.iter().collect::<Vec<_>>(),
+        items(10_000).collect::<Vec<_>>()
+    );
+
+    assert_eq!(store.allocs(), store.len());
+
+    // 逐个插入会反复重写同一条路径
+    let (bulk_store, direct_store) = (SharedStore::default(), SharedStore::default());
+    let mut bulk = BTree::with_store(Box::new(bulk_store.clone()));
+    bulk.bulk_load(items(2000)).unwrap();
+    let mut direct = BTree::with_store(Box::new(direct_store.clone()));
+    for (key, val) in items(2000) {
+        direct.insert(&key, &val).unwrap();
+    }
+    assert!(bulk_store.allocs() * 10 < direct_store.allocs());
+    assert!(bulk_store.len() <= direct_store.len());
+
+    // 之后可以正常修改
+    tree.insert(b"a", b"first").unwrap();
+    tree.insert(&key(5000), b"updated").unwrap();
+    assert!(tree.delete(&key(1)).unwrap());
+    tree.check().unwrap();
+    assert_eq!(
+        tree.get_value(&key(5000)).unwrap(),
+        Some(b"updated".to_vec())
+    );
+    assert_eq!(tree.len().unwrap(), 10_000);
+}
+
+#[test]
+fn bulk_load_small_inputs() {
+    let mut tree = new_tree();
+    tree.bulk_load(items(0)).unwrap();
+    assert_eq!(tree.root_ptr(), 0);
+
+    tree.bulk_load(items(1)).unwrap();
+    assert_eq!(tree.check().unwrap().nodes, 1);
+    assert_eq!(tree.get_value(&key(0)).unwrap(), Some(val(0)));
+
+    // 大 value 写入 overflow page
+    let mut tree = new_tree();
+    let big = vec![7; 100_000];
+    tree.bulk_load([(key(0), big.clone()), (key(1), val(1))].into_iter())
+        .unwrap();
+    assert_eq!(tree.get_value(&key(0)).unwrap(), Some(big));
+    assert_eq!(tree.get_value(&key(1)).unwrap(), Some(val(1)));
+}
+
+#[test]
+fn bulk_load_rejects_bad_input() {
+    let mut tree = new_tree();
+    let unsorted = [(key(2), val(2)), (key(1), val(1))];
+    assert_eq!(
+        tree.bulk_load(unsorted.into_iter()),
+        Err(BTreeError::UnsortedKeys)
+    );
+    assert_eq!(tree.root_ptr(), 0);
+
+    // 跨越叶子节点的重复 key
+    let duplicated = items(1000).chain(items(1));
+    assert_eq!(tree.bulk_load(duplicated), Err(BTreeError::UnsortedKeys));
+    assert_eq!(tree.root_ptr(), 0);
+
+    tree.insert(b"key", b"val").unwrap();
+    assert_eq!(tree.bulk_load(items(10)), Err(BTreeError::NotEmpty));
+}
+
+#[test]
+fn bulk_load_adds_sentinel() {
+    let mut tree = new_tree();
+    tree.bulk_load(items(3000)).unwrap();
+
+    let mut node = tree.get(tree.root_ptr()).unwrap();
+    while node.get_key(0).is_empty() && node.btype() == NodeType::Node as u16 {
+        node = tree.get(node.get_ptr(0)).unwrap();
+    }
+    assert_eq!(node.get_key(0), b"");
+    assert_eq!(node.btype(), NodeType::Leaf as u16);
+    assert_eq!(tree.len().unwrap(), 3000);
+    assert_eq!(tree.min().unwrap(), Some((key(0), val(0))));
+
+    let mut tree = new_tree();
+    let with_empty = [(vec![], val(0)), (key(1), val(1))];
+    assert_eq!(
+        tree.bulk_load(with_empty.into_iter()),
+        Err(BTreeError::EmptyKey)
+    );
+}
@@ -0,0 +1,102 @@
+use super::file_store::TempDb;
+use crate::storage::{
+    b_tree::{BNode, BTree, NodeType, BTREE_PAGE_SIZE},
+    cached_store::CachedStore,
+    file_store::FileStore,
+    page_store::{MemoryStore, PageStore},
+};
+
+fn leaf(i: u8) -> BNode {
+    let mut node = BNode::new(BTREE_PAGE_SIZE);
+    node.set_header(NodeType::Leaf as u16, 1);
+    node.node_append_kv(0, 0, vec![b'k', i], vec![i; 100])
+        .unwrap();
+    node
+}
+
+#[test]
+fn cached_get_matches_store() {
+    let db = TempDb::new();
+    let mut store = FileStore::open(&db.path).unwrap();
+    let ptrs: Vec<u64> = (0..10).map(|i| store.alloc(&leaf(i)).unwrap()).collect();
+    store.commit(0).unwrap();
+    drop(store);
+
+    // 缓存为空时从 store 中读取
+    let store = CachedStore::new(FileStore::open(&db.path).unwrap(), 4);
+    let plain = FileStore::open(&db.path).unwrap();
+    for ptr in &ptrs {
+        assert!(!store.contains(*ptr));
+        let cached = store.get(*ptr).unwrap();
+        assert!(store.contains(*ptr));
+        assert!(store.len() <= 4);
+        assert_eq!(cached.data, plain.get(*ptr).unwrap().data);
+        assert_eq!(store.get(*ptr).unwrap().data, cached.data);
+    }
+
+    // alloc 写入的 page 与 store 中的相同
+    let mut store = CachedStore::new(MemoryStore::new(), 4);
+    let ptr = store.alloc(&leaf(42)).unwrap();
+    assert!(store.contains(ptr));
+    let cached = store.get(ptr).unwrap();
+    let mut inner = MemoryStore::new();
+    let inner_ptr = inner.alloc(&leaf(42)).unwrap();
+    assert_eq!(cached.data, inner.get(inner_ptr).unwrap().data);
+}
+
+#[test]
+fn free_evicts_page() {
+    let mut store = CachedStore::new(MemoryStore::new(), 4);
+    let ptr = store.alloc(&leaf(1)).unwrap();
+    assert!(store.contains(ptr));
+
+    store.free(ptr);
+    assert!(!store.contains(ptr));
+    assert!(store.get(ptr).is_err());
+    assert!(store.is_empty());
+}
+
+#[test]
+fn least_recently_used_page_is_evicted() {
+    let mut store = CachedStore::new(MemoryStore::new(), 3);
+    let a = store.alloc(&leaf(1)).unwrap();
+    let b = store.alloc(&leaf(2)).unwrap();
+    let c = store.alloc(&leaf(3)).unwrap();
+    assert_eq!(store.len(), 3);
+
+    // 访问 a 之后，b 成为最久没有被访问的 page
+    store.get(a).unwrap();
+    let d = store.alloc(&leaf(4)).unwrap();
+    assert_eq!(store.len(), 3);
+    assert!(store.contains(a));
+    assert!(!store.contains(b));
+    assert!(store.contains(c));
+    assert!(store.contains(d));
+
+    // 被淘汰的 page 仍然可以从 store 中读取，并重新进入缓存
+    assert_eq!(store.get(b).unwrap().get_key(0), vec![b'k', 2]);
+    assert!(store.contains(b));
+    assert!(!store.contains(c));
+}
+
+#[test]
+fn btree_over_cached_store() {
+    let db = TempDb::new();
+    let store = CachedStore::new(FileStore::open(&db.path).unwrap(), 16);
+    let mut tree = BTree::with_store(Box::new(store));
+    for i in 0..500_u32 {
+        tree.insert(format!("key{i:04}").as_bytes(), &[i as u8; 100])
+            .unwrap();
+    }
+    tree.commit().unwrap();
+    drop(tree);
+
+    let tree = BTree::with_store(Box::new(CachedStore::new(
+        FileStore::open(&db.path).unwrap(),
+        16,
+    )));
+    for i in 0..500_u32 {
+        let val = tree.get_value(format!("key{i:04}").as_bytes()).unwrap();
+        assert_eq!(val, Some(vec![i as u8; 100]));
+    }
+}
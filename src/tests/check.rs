@@ -0,0 +1,120 @@
+use rand::seq::SliceRandom;
+
+use super::util::{key, new_tree};
+use crate::storage::{
+    b_tree::{BNode, NodeType, BTREE_PAGE_SIZE},
+    check::CheckReport,
+    error::BTreeError,
+};
+
+fn leaf(keys: &[&[u8]]) -> BNode {
+    let mut node = BNode::new(BTREE_PAGE_SIZE);
+    node.set_header(NodeType::Leaf as u16, keys.len() as u16);
+    for (i, key) in keys.iter().enumerate() {
+        node.node_append_kv(i as u16, 0, key.to_vec(), b"val".to_vec())
+            .unwrap();
+    }
+    node
+}
+
+#[test]
+fn check_well_formed_tree() {
+    let mut tree = new_tree();
+    assert_eq!(tree.check().unwrap(), CheckReport::default());
+
+    tree.insert(b"k", b"v").unwrap();
+    let report = tree.check().unwrap();
+    assert_eq!((report.nodes, report.leaves, report.height), (1, 1, 1));
+
+    let mut ids: Vec<u32> = (0..2000).collect();
+    ids.shuffle(&mut rand::thread_rng());
+    for i in &ids {
+        tree.insert(&key(*i), &[*i as u8; 300]).unwrap();
+    }
+    let report = tree.check().unwrap();
+    assert!(report.height >= 3);
+    assert!(report.leaves > 100);
+    assert!(report.nodes > report.leaves);
+
+    for (n, i) in ids[..1500].iter().enumerate() {
+        tree.delete(&key(*i)).unwrap();
+        if n % 100 == 0 {
+            assert!(tree.check().is_ok());
+        }
+    }
+    assert!(tree.check().unwrap().leaves < report.leaves);
+}
+
+#[test]
+fn check_detects_unordered_keys() {
+    let mut tree = new_tree();
+    let ptr = tree.new(&leaf(&[b"b", b"a"])).unwrap();
+    tree.set_root(ptr);
+    assert_eq!(tree.check(), Err(BTreeError::CorruptPage));
+
+    let ptr = tree.new(&leaf(&[b"a", b"a"])).unwrap();
+    tree.set_root(ptr);
+    assert_eq!(tree.check(), Err(BTreeError::CorruptPage));
+}
+
+#[test]
+fn check_detects_bad_internal_nodes() {
+    let mut tree = new_tree();
+    let left = tree.new(&leaf(&[b"a", b"b"])).unwrap();
+    let right = tree.new(&leaf(&[b"c", b"d"])).unwrap();
+
+    let mut root = BNode::new(BTREE_PAGE_SIZE);
+    root.set_header(NodeType::Node as u16, 2);
+    root.node_append_kv(0, left, b"a".to_vec(), vec![]).unwrap();
+    root.node_append_kv(1, right, b"c".to_vec(), vec![])
+        .unwrap();
+    let ptr = tree.new(&root).unwrap();
+    tree.set_root(ptr);
+    let report = tree.check().unwrap();
+    assert_eq!((report.nodes, report.leaves, report.height), (3, 2, 2));
+
+    // 内部节点的 key 与子节点的第一个 key 不同
+    let mut root = BNode::new(BTREE_PAGE_SIZE);
+    root.set_header(NodeType::Node as u16, 2);
+    root.node_append_kv(0, left, b"a".to_vec(), vec![]).unwrap();
+    root.node_append_kv(1, right, b"bb".to_vec(), vec![])
+        .unwrap();
+    let ptr = tree.new(&root).unwrap();
+    tree.set_root(ptr);
+    assert_eq!(tree.check(), Err(BTreeError::CorruptPage));
+
+    // 叶子节点的深度不同
+    let mut mid = BNode::new(BTREE_PAGE_SIZE);
+    mid.set_header(NodeType::Node as u16, 1);
+    mid.node_append_kv(0, right, b"c".to_vec(), vec![]).unwrap();
+    let mid = tree.new(&mid).unwrap();
+    let mut root = BNode::new(BTREE_PAGE_SIZE);
+    root.set_header(NodeType::Node as u16, 2);
+    root.node_append_kv(0, left, b"a".to_vec(), vec![]).unwrap();
+    root.node_append_kv(1, mid, b"c".to_vec(), vec![]).unwrap();
+    let ptr = tree.new(&root).unwrap();
+    tree.set_root(ptr);
+    assert_eq!(tree.check(), Err(BTreeError::CorruptPage));
+
+    // 无效的子节点指针
+    let mut root = BNode::new(BTREE_PAGE_SIZE);
+    root.set_header(NodeType::Node as u16, 2);
+    root.node_append_kv(0, left, b"a".to_vec(), vec![]).unwrap();
+    root.node_append_kv(1, 999, b"c".to_vec(), vec![]).unwrap();
+    let ptr = tree.new(&root).unwrap();
+    tree.set_root(ptr);
+    assert_eq!(tree.check(), Err(BTreeError::CorruptPage));
+}
+
+#[test]
+fn check_detects_pointer_cycles() {
+    let mut tree = new_tree();
+    // 新的 MemoryStore 从 1 开始分配，这个内部节点指向它自己
+    let mut node = BNode::new(BTREE_PAGE_SIZE);
+    node.set_header(NodeType::Node as u16, 1);
+    node.node_append_kv(0, 1, vec![], vec![]).unwrap();
+    let ptr = tree.new(&node).unwrap();
+    assert_eq!(ptr, 1);
+    tree.set_root(ptr);
+    assert_eq!(tree.check(), Err(BTreeError::CorruptPage));
+}
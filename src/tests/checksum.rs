@@ -0,0 +1,23 @@
+use crate::storage::{
+    checksum::{crc32, crc32_update, seal_page, verify_page},
+    error::BTreeError,
+};
+
+#[test]
+fn crc32_known_values() {
+    assert_eq!(crc32(b""), 0);
+    assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    assert_eq!(crc32_update(crc32(b"1234"), b"56789"), 0xCBF4_3926);
+}
+
+#[test]
+fn verify_page_detects_flipped_byte() {
+    let mut page = vec![0_u8; 4096];
+    page[0] = 2;
+    page[100] = 0x5a;
+    seal_page(&mut page);
+    assert_eq!(verify_page(&page), Ok(()));
+
+    page[200] ^= 1;
+    assert_eq!(verify_page(&page), Err(BTreeError::CorruptPage));
+}
@@ -0,0 +1,120 @@
+use rand::Rng;
+
+use super::shared_store::SharedStore;
+use crate::storage::{
+    b_tree::{BTree, NodeType},
+    codec::{NoCompression, RunLength, ValueCodec},
+    error::BTreeError,
+};
+
+// 3 KB，大于 BTREE_MAX_VAL_SIZE，不压缩时需要 overflow page
+fn compressible() -> Vec<u8> {
+    let mut val = vec![];
+    for i in 0..12 {
+        val.extend(std::iter::repeat_n(b'a' + i, 256));
+    }
+    val
+}
+
+fn root_is_inline_leaf(tree: &BTree) -> bool {
+    let root = tree.get(tree.root_ptr()).unwrap();
+    root.btype() == NodeType::Leaf as u16 && (0..root.nkeys()).all(|i| !root.is_overflow(i))
+}
+
+#[test]
+fn run_length_round_trip() {
+    let codec = RunLength;
+    for val in [vec![], vec![1], vec![7; 1000], compressible()] {
+        let data = codec.compress(&val);
+        assert_eq!(codec.decompress(&data).unwrap(), val);
+    }
+    assert_eq!(codec.compress(&[5; 300]), [255, 5, 45, 5]);
+
+    let mut rng = rand::thread_rng();
+    let val: Vec<u8> = (0..1000).map(|_| rng.gen_range(0..4)).collect();
+    assert_eq!(codec.decompress(&codec.compress(&val)).unwrap(), val);
+
+    assert_eq!(codec.decompress(&[1]), Err(BTreeError::CorruptPage));
+    assert_eq!(codec.decompress(&[0, 1]), Err(BTreeError::CorruptPage));
+}
+
+#[test]
+fn compressed_value_fits_inline() {
+    let val = compressible();
+
+    let store = SharedStore::default();
+    let mut tree = BTree::with_store(Box::new(store.clone())).with_codec(Box::new(RunLength));
+    tree.insert(b"json", &val).unwrap();
+    assert_eq!(store.len(), 1);
+    assert!(root_is_inline_leaf(&tree));
+    assert_eq!(tree.get_value(b"json").unwrap(), Some(val.clone()));
+    assert_eq!(tree.iter().next().unwrap().1, val);
+    let mut cursor = tree.cursor();
+    assert!(cursor.seek(b"json").unwrap());
+    assert_eq!(cursor.current().unwrap().1, val.as_slice());
+
+    // 不压缩时需要 overflow page
+    let store = SharedStore::default();
+    let mut tree = BTree::with_store(Box::new(store.clone()));
+    tree.insert(b"json", &val).unwrap();
+    assert!(store.len() > 1);
+    assert!(!root_is_inline_leaf(&tree));
+}
+
+#[test]
+fn incompressible_values_are_stored_raw() {
+    let mut rng = rand::thread_rng();
+    let store = SharedStore::default();
+    let mut tree = BTree::with_store(Box::new(store.clone())).with_codec(Box::new(RunLength));
+
+    let mut vals = vec![];
+    for i in 0..200_u32 {
+        let val: Vec<u8> = (0..rng.gen_range(0..5000)).map(|_| rng.gen()).collect();
+        tree.insert(&i.to_be_bytes(), &val).unwrap();
+        vals.push(val);
+    }
+    tree.insert(b"empty", b"").unwrap();
+    tree.upsert(b"counter", &[1; 10], |old| old.repeat(2))
+        .unwrap();
+    tree.upsert(b"counter", &[1; 10], |old| old.repeat(2))
+        .unwrap();
+    tree.check().unwrap();
+
+    for (i, val) in vals.iter().enumerate() {
+        assert_eq!(
+            tree.get_value(&(i as u32).to_be_bytes()).unwrap().as_ref(),
+            Some(val)
+        );
+    }
+    assert_eq!(tree.get_value(b"empty").unwrap(), Some(vec![]));
+    assert_eq!(tree.get_value(b"counter").unwrap(), Some(vec![1; 20]));
+
+    tree.clear().unwrap();
+    assert_eq!(store.len(), 0);
+}
+
+#[test]
+fn no_compression_codec_round_trips() {
+    let mut tree =
+        BTree::with_store(Box::new(SharedStore::default())).with_codec(Box::new(NoCompression));
+    let val = compressible();
+    tree.insert(b"key", &val).unwrap();
+    assert_eq!(tree.get_value(b"key").unwrap(), Some(val));
+}
+
+#[cfg(feature = "flate2")]
+#[test]
+fn deflate_compresses_json() {
+    use crate::storage::codec::Deflate;
+
+    let val = r#"{"id": 1, "name": "widget", "tags": ["a", "b"]}"#
+        .repeat(60)
+        .into_bytes();
+    assert!(val.len() > 2800);
+
+    let store = SharedStore::default();
+    let mut tree = BTree::with_store(Box::new(store.clone())).with_codec(Box::new(Deflate));
+    tree.insert(b"json", &val).unwrap();
+    assert_eq!(store.len(), 1);
+    assert_eq!(tree.get_value(b"json").unwrap(), Some(val));
+}
@@ -0,0 +1,82 @@
+use std::{cmp::Ordering, ops::Bound};
+
+use crate::storage::{
+    b_tree::{BTree, BTreeConfig},
+    comparator::KeyComparator,
+    page_store::MemoryStore,
+};
+
+// 忽略 ASCII 大小写
+struct CaseInsensitive;
+
+impl KeyComparator for CaseInsensitive {
+    fn compare(&self, a: &[u8], b: &[u8]) -> Ordering {
+        a.iter()
+            .map(u8::to_ascii_lowercase)
+            .cmp(b.iter().map(u8::to_ascii_lowercase))
+    }
+}
+
+fn new_tree() -> BTree<CaseInsensitive> {
+    BTree::with_comparator(
+        Box::new(MemoryStore::new()),
+        BTreeConfig::default(),
+        CaseInsensitive,
+    )
+    .unwrap()
+}
+
+#[test]
+fn case_insensitive_keys_collide() {
+    let mut tree = new_tree();
+    tree.insert(b"ABC", b"1").unwrap();
+    tree.insert(b"abc", b"2").unwrap();
+
+    assert_eq!(tree.len().unwrap(), 1);
+    assert_eq!(tree.get_value(b"aBc").unwrap(), Some(b"2".to_vec()));
+    assert_eq!(tree.iter().count(), 1);
+
+    assert!(tree.delete(b"Abc").unwrap());
+    assert!(tree.is_empty());
+}
+
+#[test]
+fn case_insensitive_order() {
+    let mut tree = new_tree();
+    for i in 0..2000 {
+        let key = format!("key{:05}", i);
+        let key = if i % 2 == 0 { key.to_uppercase() } else { key };
+        tree.insert(key.as_bytes(), &[1; 100]).unwrap();
+    }
+    // 另一种大小写的写法更新的是同一个 key
+    for i in 0..2000 {
+        let key = format!("KEY{:05}", i);
+        tree.insert(key.as_bytes(), &[2; 100]).unwrap();
+    }
+    tree.check().unwrap();
+    assert_eq!(tree.len().unwrap(), 2000);
+
+    // 按字节比较时大写字母都排在小写字母之前，这里应当交替出现
+    let keys: Vec<_> = tree
+        .iter()
+        .map(|(key, _)| key.to_ascii_lowercase())
+        .collect();
+    let expected: Vec<_> = (0..2000)
+        .map(|i| format!("key{:05}", i).into_bytes())
+        .collect();
+    assert_eq!(keys, expected);
+    assert!(tree.iter().all(|(_, val)| val == [2; 100]));
+
+    let range: Vec<_> = tree
+        .scan(Bound::Included(b"Key00010"), Bound::Excluded(b"kEY00013"))
+        .map(|(key, _)| key.to_ascii_lowercase())
+        .collect();
+    assert_eq!(
+        range,
+        [
+            b"key00010".to_vec(),
+            b"key00011".to_vec(),
+            b"key00012".to_vec()
+        ]
+    );
+}
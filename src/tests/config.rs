@@ -0,0 +1,107 @@
+use super::{b_tree_delete::check_tree, util::key};
+use crate::storage::{
+    b_tree::{BTree, BTreeConfig, NodeType},
+    error::BTreeError,
+    page_store::MemoryStore,
+};
+
+fn small_config() -> BTreeConfig {
+    BTreeConfig {
+        page_size: 256,
+        max_key_size: 16,
+        max_val_size: 64,
+        max_blob_size: 1024,
+        ..BTreeConfig::default()
+    }
+}
+
+fn val(i: u32) -> Vec<u8> {
+    format!("v{:05}", i)
+        .repeat(i as usize % 10 + 1)
+        .into_bytes()
+}
+
+#[test]
+fn default_config_is_valid() {
+    assert_eq!(BTreeConfig::default().validate(), Ok(()));
+    assert_eq!(small_config().validate(), Ok(()));
+}
+
+#[test]
+fn invalid_config_is_rejected() {
+    let config = BTreeConfig {
+        max_val_size: 256,
+        ..small_config()
+    };
+    assert_eq!(config.validate(), Err(BTreeError::InvalidConfig));
+
+    let config = BTreeConfig {
+        page_size: 1 << 16,
+        ..BTreeConfig::default()
+    };
+    assert_eq!(config.validate(), Err(BTreeError::InvalidConfig));
+
+    // page 大小与 store 不一致
+    let store = Box::new(MemoryStore::new());
+    assert!(matches!(
+        BTree::with_config(store, small_config()),
+        Err(BTreeError::InvalidConfig)
+    ));
+}
+
+#[test]
+fn with_store_follows_store_page_size() {
+    assert_eq!(BTreeConfig::with_page_size(4096), BTreeConfig::default());
+    for page_size in [256, 1024, 16 << 10] {
+        let config = BTreeConfig::with_page_size(page_size);
+        assert_eq!(config.validate(), Ok(()));
+
+        let mut tree = BTree::with_store(Box::new(MemoryStore::with_page_size(page_size)));
+        assert_eq!(tree.config(), &config);
+        for i in 0..300 {
+            tree.insert(&key(i), &val(i)).unwrap();
+        }
+        // 超过 max_val_size 的 value 进入 overflow page
+        tree.insert(b"big", &vec![7; 3 * page_size]).unwrap();
+        check_tree(&tree);
+        assert_eq!(tree.get_value(&key(7)).unwrap(), Some(val(7)));
+        assert_eq!(
+            tree.get_value(b"big").unwrap(),
+            Some(vec![7; 3 * page_size])
+        );
+    }
+}
+
+#[test]
+fn small_pages_split_into_valid_nodes() {
+    let config = small_config();
+    let store = Box::new(MemoryStore::with_page_size(config.page_size));
+    let mut tree = BTree::with_config(store, config).unwrap();
+
+    for i in 0..500 {
+        tree.insert(&key(i), &val(i)).unwrap();
+    }
+    check_tree(&tree);
+    assert_eq!(
+        tree.get(tree.root_ptr()).unwrap().node_type(),
+        Ok(NodeType::Node)
+    );
+    for i in 0..500 {
+        assert_eq!(tree.get_value(&key(i)).unwrap(), Some(val(i)));
+    }
+
+    assert_eq!(tree.insert(&[b'k'; 17], b"v"), Err(BTreeError::KeyTooLong));
+    assert_eq!(
+        tree.insert(b"k", &[b'v'; 1025]),
+        Err(BTreeError::ValueTooLong)
+    );
+
+    for i in (0..500).step_by(2) {
+        assert!(tree.delete(&key(i)).unwrap());
+    }
+    check_tree(&tree);
+    for i in 0..500 {
+        let expected = if i % 2 == 0 { None } else { Some(val(i)) };
+        assert_eq!(tree.get_value(&key(i)).unwrap(), expected);
+    }
+}
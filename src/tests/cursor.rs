@@ -0,0 +1,183 @@
+use rand::Rng;
+
+use super::{shared_store::SharedStore, util::new_tree};
+use crate::storage::{b_tree::BTree, cursor::Cursor};
+
+// 只有偶数，用奇数 seek 到两个 key 之间
+fn key(i: u32) -> Vec<u8> {
+    format!("key{:04}", 2 * i).into_bytes()
+}
+
+fn between(i: u32) -> Vec<u8> {
+    format!("key{:04}", 2 * i + 1).into_bytes()
+}
+
+fn val(i: u32) -> Vec<u8> {
+    format!("val{:04}", i).repeat(20).into_bytes()
+}
+
+fn populated() -> BTree {
+    let mut tree = new_tree();
+    for i in 0..500 {
+        tree.insert(&key(i), &val(i)).unwrap();
+    }
+    assert!(tree.check().unwrap().height > 1);
+    tree
+}
+
+fn current_key(cursor: &Cursor) -> Vec<u8> {
+    cursor.current().unwrap().0.to_vec()
+}
+
+#[test]
+fn cursor_steps_forward_and_back() {
+    let tree = populated();
+    let mut cursor = tree.cursor();
+    assert_eq!(cursor.current(), None);
+
+    assert!(cursor.seek(&key(250)).unwrap());
+    let (k, v) = cursor.current().unwrap();
+    assert_eq!((k, v), (key(250).as_slice(), val(250).as_slice()));
+
+    let mut visited = vec![current_key(&cursor)];
+    for _ in 0..10 {
+        assert!(cursor.next().unwrap());
+        visited.push(current_key(&cursor));
+    }
+    for _ in 0..20 {
+        assert!(cursor.prev().unwrap());
+        visited.push(current_key(&cursor));
+    }
+
+    let expected: Vec<_> = (250..=260).chain((240..260).rev()).map(key).collect();
+    assert_eq!(visited, expected);
+}
+
+#[test]
+fn cursor_seek() {
+    let tree = populated();
+    let mut cursor = tree.cursor();
+
+    // seek 到第一个 >= key 的位置
+    assert!(cursor.seek(&between(100)).unwrap());
+    assert_eq!(current_key(&cursor), key(101));
+    assert!(cursor.seek(b"").unwrap());
+    assert_eq!(current_key(&cursor), key(0));
+    assert!(cursor.seek(b"a").unwrap());
+    assert_eq!(current_key(&cursor), key(0));
+    assert!(!cursor.seek(&between(499)).unwrap());
+    assert_eq!(cursor.current(), None);
+
+    // seek_le 到最后一个 <= key 的位置
+    assert!(cursor.seek_le(&between(100)).unwrap());
+    assert_eq!(current_key(&cursor), key(100));
+    assert!(cursor.seek_le(&key(100)).unwrap());
+    assert_eq!(current_key(&cursor), key(100));
+    assert!(cursor.seek_le(b"z").unwrap());
+    assert_eq!(current_key(&cursor), key(499));
+    assert!(!cursor.seek_le(b"a").unwrap());
+    assert_eq!(cursor.current(), None);
+
+    // 每个叶子节点的边界都可以正确跨越
+    assert!(cursor.seek(b"").unwrap());
+    for i in 1..500 {
+        assert!(cursor.next().unwrap());
+        assert_eq!(current_key(&cursor), key(i));
+    }
+    for i in (0..499).rev() {
+        assert!(cursor.prev().unwrap());
+        assert_eq!(current_key(&cursor), key(i));
+    }
+}
+
+#[test]
+fn cursor_stops_at_both_ends() {
+    let tree = populated();
+    let mut cursor = tree.cursor();
+
+    // 未定位时不能移动
+    assert!(!cursor.next().unwrap());
+    assert!(!cursor.prev().unwrap());
+
+    assert!(cursor.seek(&key(498)).unwrap());
+    assert!(cursor.next().unwrap());
+    assert!(!cursor.next().unwrap());
+    assert_eq!(current_key(&cursor), key(499));
+    assert!(cursor.prev().unwrap());
+    assert_eq!(current_key(&cursor), key(498));
+
+    // 不会停在哨兵上
+    assert!(cursor.seek(&key(1)).unwrap());
+    assert!(cursor.prev().unwrap());
+    assert!(!cursor.prev().unwrap());
+    assert_eq!(current_key(&cursor), key(0));
+    assert!(cursor.next().unwrap());
+    assert_eq!(current_key(&cursor), key(1));
+
+    let empty = new_tree();
+    let mut cursor = empty.cursor();
+    assert!(!cursor.seek(b"key").unwrap());
+    assert!(!cursor.seek_le(b"key").unwrap());
+    assert_eq!(cursor.current(), None);
+}
+
+#[test]
+fn cursor_reads_overflow_values() {
+    let mut tree = populated();
+    let big = vec![7; 10_000];
+    tree.insert(&between(10), &big).unwrap();
+
+    let mut cursor = tree.cursor();
+    assert!(cursor.seek(&key(10)).unwrap());
+    assert!(cursor.next().unwrap());
+    assert_eq!(cursor.current().unwrap().1, big.as_slice());
+    assert!(cursor.next().unwrap());
+    assert_eq!(cursor.current().unwrap().1, val(11).as_slice());
+    assert!(cursor.prev().unwrap());
+    assert_eq!(cursor.current().unwrap().1, big.as_slice());
+}
+
+#[test]
+fn get_batch_matches_get_value() {
+    let store = SharedStore::default();
+    let mut tree = BTree::with_store(Box::new(store.clone()));
+    for i in 0..500 {
+        tree.insert(&key(i), &val(i)).unwrap();
+    }
+
+    // 一半存在，一半落在两个 key 之间，还有重复的 key
+    let mut rng = rand::thread_rng();
+    let lookups: Vec<Vec<u8>> = (0..1000)
+        .map(|_| {
+            let i = rng.gen_range(0..550);
+            if rng.gen() {
+                key(i)
+            } else {
+                between(i)
+            }
+        })
+        .chain([vec![], b"zzz".to_vec()])
+        .collect();
+    let keys: Vec<&[u8]> = lookups.iter().map(|key| key.as_slice()).collect();
+
+    let before = store.reads();
+    let batch = tree.get_batch(&keys).unwrap();
+    let batch_reads = store.reads() - before;
+
+    let before = store.reads();
+    let single: Vec<_> = keys
+        .iter()
+        .map(|key| tree.get_value(key).unwrap())
+        .collect();
+    let single_reads = store.reads() - before;
+
+    assert_eq!(batch, single);
+    assert!(batch.iter().filter(|val| val.is_some()).count() > 300);
+    // 每个 page 最多读取一次
+    assert!(batch_reads <= tree.check().unwrap().nodes);
+    assert!(
+        batch_reads * 2 < single_reads,
+        "{batch_reads} vs {single_reads}"
+    );
+    assert!(tree.get_batch(&[]).unwrap().is_empty());
+}
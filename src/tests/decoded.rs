@@ -0,0 +1,89 @@
+use super::util::new_tree;
+use crate::storage::{
+    b_tree::{BNode, NodeType, BTREE_PAGE_SIZE},
+    decoded::{DecodedKv, DecodedNode},
+    error::BTreeError,
+};
+
+fn sample_leaf() -> BNode {
+    let mut node = BNode::new(BTREE_PAGE_SIZE);
+    node.set_header(NodeType::Leaf as u16, 3);
+    node.node_append_kv(0, 0, b"".to_vec(), b"".to_vec())
+        .unwrap();
+    node.node_append_kv(1, 0, b"apple".to_vec(), b"red".to_vec())
+        .unwrap();
+    node.node_append_kv(2, 0, b"banana".to_vec(), vec![0; 16])
+        .unwrap();
+    node.set_overflow(2);
+    node
+}
+
+#[test]
+fn decoded_node_round_trip() {
+    let node = sample_leaf();
+    let decoded = DecodedNode::from(&node);
+    assert_eq!(decoded.btype, NodeType::Leaf as u16);
+    assert_eq!(decoded.keys.len(), 3);
+    assert_eq!(decoded.keys[1].key, b"apple");
+    assert_eq!(decoded.keys[1].val, b"red");
+    assert!(!decoded.keys[1].overflow);
+    assert!(decoded.keys[2].overflow);
+
+    let encoded = decoded.to_node(BTREE_PAGE_SIZE).unwrap();
+    assert_eq!(encoded.data, node.data);
+}
+
+#[test]
+fn tree_pages_re_encode_exactly() {
+    let mut tree = new_tree();
+    for i in 0..300 {
+        tree.insert(format!("key{i:04}").as_bytes(), &[i as u8; 50])
+            .unwrap();
+    }
+
+    let mut ptrs = vec![tree.root_ptr()];
+    while let Some(ptr) = ptrs.pop() {
+        let mut page = tree.get(ptr).unwrap();
+        let decoded = DecodedNode::from(&page);
+        if decoded.btype == NodeType::Node as u16 {
+            ptrs.extend(decoded.keys.iter().map(|kv| kv.ptr));
+        }
+
+        // checksum 不属于节点的内容
+        page.data[4..8].fill(0);
+        assert_eq!(decoded.to_node(BTREE_PAGE_SIZE).unwrap().data, page.data);
+    }
+}
+
+#[test]
+fn invalid_decoded_node_is_rejected() {
+    let mut decoded = DecodedNode::from(&sample_leaf());
+    decoded.btype = 9;
+    assert_eq!(
+        decoded.to_node(BTREE_PAGE_SIZE).unwrap_err(),
+        BTreeError::InvalidNodeType(9)
+    );
+
+    decoded.btype = NodeType::Leaf as u16;
+    decoded.keys.push(DecodedKv {
+        key: b"cherry".to_vec(),
+        val: vec![1; BTREE_PAGE_SIZE],
+        ptr: 0,
+        overflow: false,
+    });
+    assert_eq!(
+        decoded.to_node(BTREE_PAGE_SIZE).unwrap_err(),
+        BTreeError::PageTooLarge
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn decoded_node_json_round_trip() {
+    let node = sample_leaf();
+    let json = serde_json::to_string(&DecodedNode::from(&node)).unwrap();
+    assert!(json.starts_with(r#"{"btype":2,"keys":[{"key":[],"val":[],"ptr":0"#));
+
+    let decoded: DecodedNode = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded.to_node(BTREE_PAGE_SIZE).unwrap().data, node.data);
+}
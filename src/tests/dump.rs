@@ -0,0 +1,98 @@
+use std::fs;
+
+use super::{
+    file_store::TempDb,
+    util::{key, new_tree},
+};
+use crate::storage::{
+    b_tree::BTree, error::BTreeError, file_store::FileStore, page_store::MemoryStore,
+};
+
+fn val(i: u32) -> Vec<u8> {
+    format!("val{:05}", i).repeat(i as usize % 20).into_bytes()
+}
+
+fn populated() -> BTree {
+    let mut tree = new_tree();
+    for i in (0..2000).rev() {
+        tree.insert(&key(i), &val(i)).unwrap();
+    }
+    tree.insert(b"big", &vec![9; 50_000]).unwrap();
+    tree
+}
+
+#[test]
+fn dump_restore_round_trip() {
+    let tree = populated();
+    let mut data = vec![];
+    tree.dump(&mut data).unwrap();
+
+    let restored = BTree::restore(Box::new(MemoryStore::new()), &mut data.as_slice()).unwrap();
+    restored.check().unwrap();
+    assert_eq!(
+        restored.iter().collect::<Vec<_>>(),
+        tree.iter().collect::<Vec<_>>()
+    );
+
+    // page 大小不同的 store
+    let store = MemoryStore::with_page_size(1024);
+    let restored = BTree::restore(Box::new(store), &mut data.as_slice()).unwrap();
+    restored.check().unwrap();
+    assert_eq!(restored.config().page_size, 1024);
+    assert_eq!(
+        restored.iter().collect::<Vec<_>>(),
+        tree.iter().collect::<Vec<_>>()
+    );
+
+    // 第一条记录
+    assert_eq!(&data[..4], &3_u32.to_le_bytes());
+    assert_eq!(&data[4..7], b"big");
+
+    // 空树
+    let empty = new_tree();
+    let mut data = vec![];
+    empty.dump(&mut data).unwrap();
+    assert!(data.is_empty());
+    let restored = BTree::restore(Box::new(MemoryStore::new()), &mut data.as_slice()).unwrap();
+    assert_eq!(restored.root_ptr(), 0);
+}
+
+#[test]
+fn dump_to_path_and_restore_into_file_store() {
+    let tree = populated();
+    let db = TempDb::new();
+    let dump_path = db.path.with_extension("dump");
+    tree.dump_to_path(&dump_path).unwrap();
+
+    let mut file = fs::File::open(&dump_path).unwrap();
+    let store = FileStore::open(&db.path).unwrap();
+    let mut restored = BTree::restore(Box::new(store), &mut file).unwrap();
+    restored.commit().unwrap();
+    drop(restored);
+    fs::remove_file(&dump_path).unwrap();
+
+    let reopened = db.open();
+    assert_eq!(
+        reopened.iter().collect::<Vec<_>>(),
+        tree.iter().collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn restore_rejects_truncated_stream() {
+    let tree = populated();
+    let mut data = vec![];
+    tree.dump(&mut data).unwrap();
+
+    for len in [2, 5, data.len() / 2, data.len() - 1] {
+        let truncated = &data[..len];
+        let result = BTree::restore(Box::new(MemoryStore::new()), &mut &truncated[..]);
+        assert!(
+            matches!(
+                result,
+                Err(BTreeError::Io(std::io::ErrorKind::UnexpectedEof))
+            ),
+            "{len}"
+        );
+    }
+}
@@ -0,0 +1,493 @@
+use std::{fs, os::unix::fs::FileExt, path::PathBuf};
+
+use rand::Rng;
+
+use super::util::key;
+use crate::storage::{
+    b_tree::{BTree, BTreeConfig, Durability, BTREE_PAGE_SIZE},
+    error::BTreeError,
+    file_store::FileStore,
+    index::Index,
+};
+
+// 测试用的数据库文件，drop 时删除
+pub struct TempDb {
+    pub path: PathBuf,
+}
+
+impl TempDb {
+    pub fn new() -> Self {
+        let random_int = rand::thread_rng().gen_range(0..i32::MAX);
+        let path = std::env::temp_dir().join(format!("btree_test_{random_int}.db"));
+        TempDb { path }
+    }
+
+    pub fn open(&self) -> BTree {
+        BTree::with_store(Box::new(FileStore::open(&self.path).unwrap()))
+    }
+
+    pub fn open_with(&self, durability: Durability) -> BTree {
+        let config = BTreeConfig {
+            durability,
+            ..BTreeConfig::default()
+        };
+        BTree::with_config(Box::new(FileStore::open(&self.path).unwrap()), config).unwrap()
+    }
+}
+
+impl Drop for TempDb {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+        let _ = fs::remove_file(format!("{}.master", self.path.to_string_lossy()));
+        let _ = fs::remove_file(format!("{}.wal", self.path.to_string_lossy()));
+    }
+}
+
+fn val(i: u32) -> Vec<u8> {
+    format!("val{:06}", i).repeat(10).into_bytes()
+}
+
+#[test]
+fn file_store_survives_reopen() {
+    let db = TempDb::new();
+
+    let mut tree = db.open();
+    for i in 0..1000 {
+        tree.insert(&key(i), &val(i)).unwrap();
+    }
+    tree.commit().unwrap();
+    drop(tree);
+
+    let mut tree = db.open();
+    for i in 0..1000 {
+        assert_eq!(tree.get_value(&key(i)).unwrap(), Some(val(i)));
+    }
+
+    // 未提交的修改在重新打开后不可见
+    tree.insert(b"uncommitted", b"value").unwrap();
+    for i in 0..10 {
+        tree.delete(&key(i)).unwrap();
+    }
+    drop(tree);
+
+    let mut tree = db.open();
+    assert_eq!(tree.get_value(b"uncommitted").unwrap(), None);
+    assert_eq!(tree.get_value(&key(0)).unwrap(), Some(val(0)));
+
+    for i in 1000..2000 {
+        tree.insert(&key(i), &val(i)).unwrap();
+    }
+    tree.commit().unwrap();
+    drop(tree);
+
+    let tree = db.open();
+    for i in 0..2000 {
+        assert_eq!(tree.get_value(&key(i)).unwrap(), Some(val(i)));
+    }
+}
+
+#[test]
+fn file_store_empty_database() {
+    let db = TempDb::new();
+
+    let mut tree = db.open();
+    assert_eq!(tree.root_ptr(), 0);
+    assert_eq!(tree.get_value(b"k").unwrap(), None);
+    tree.commit().unwrap();
+    drop(tree);
+
+    let tree = db.open();
+    assert_eq!(tree.root_ptr(), 0);
+}
+
+#[test]
+fn free_list_page_count_stabilizes() {
+    let db = TempDb::new();
+
+    let mut tree = db.open();
+    for i in 0..500 {
+        tree.insert(&key(i), &val(i)).unwrap();
+    }
+    tree.commit().unwrap();
+    drop(tree);
+
+    let mut counts = vec![];
+    for round in 0..20 {
+        // 每一轮重新打开，free list 需要从文件中读回
+        let store = FileStore::open(&db.path).unwrap();
+        counts.push(store.page_count());
+        let mut tree = BTree::with_store(Box::new(store));
+
+        for i in 0..100 {
+            tree.insert(&key(1000 + i), &val(round)).unwrap();
+        }
+        tree.commit().unwrap();
+        for i in 0..100 {
+            tree.delete(&key(1000 + i)).unwrap();
+        }
+        tree.commit().unwrap();
+    }
+
+    // 前几轮之后，文件不再增长
+    assert_eq!(counts[5], counts[19], "{counts:?}");
+
+    let tree = db.open();
+    for i in 0..500 {
+        assert_eq!(tree.get_value(&key(i)).unwrap(), Some(val(i)));
+    }
+    assert_eq!(tree.get_value(&key(1000)).unwrap(), None);
+}
+
+#[test]
+fn corrupted_page_is_detected() {
+    let db = TempDb::new();
+
+    let mut tree = db.open();
+    tree.insert(b"key", b"value").unwrap();
+    tree.commit().unwrap();
+    let root = tree.root_ptr();
+    drop(tree);
+
+    // 修改 root page 中的一个字节
+    let file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&db.path)
+        .unwrap();
+    let pos = root * BTREE_PAGE_SIZE as u64 + 100;
+    let mut byte = [0_u8];
+    file.read_exact_at(&mut byte, pos).unwrap();
+    file.write_all_at(&[byte[0] ^ 0xff], pos).unwrap();
+    file.sync_all().unwrap();
+    drop(file);
+
+    let tree = db.open();
+    assert_eq!(tree.get_value(b"key"), Err(BTreeError::CorruptPage));
+}
+
+#[test]
+fn clear_reuses_freed_pages() {
+    let db = TempDb::new();
+
+    let mut tree = db.open();
+    for i in 0..1000 {
+        tree.insert(&key(i), &val(i)).unwrap();
+    }
+    tree.commit().unwrap();
+    tree.clear().unwrap();
+    assert_eq!(tree.len().unwrap(), 0);
+    assert_eq!(tree.iter().count(), 0);
+    tree.commit().unwrap();
+    drop(tree);
+
+    let store = FileStore::open(&db.path).unwrap();
+    let pages = store.page_count();
+    assert!(store.free_count() > 0);
+    let mut tree = BTree::with_store(Box::new(store));
+    assert_eq!(tree.root_ptr(), 0);
+
+    // 再次插入相同的数据，使用 free list 中的 page，只有 free list 自身的 page 可能让文件略微增长
+    for i in 0..1000 {
+        tree.insert(&key(i), &val(i)).unwrap();
+    }
+    tree.commit().unwrap();
+    drop(tree);
+
+    let store = FileStore::open(&db.path).unwrap();
+    assert!(
+        store.page_count() < pages + 8,
+        "{} vs {pages}",
+        store.page_count()
+    );
+    let tree = BTree::with_store(Box::new(store));
+    assert_eq!(tree.len().unwrap(), 1000);
+}
+
+fn zero_master(db: &TempDb) {
+    let master = format!("{}.master", db.path.to_string_lossy());
+    let len = fs::metadata(&master).unwrap().len() as usize;
+    fs::write(&master, vec![0; len]).unwrap();
+}
+
+fn contents(tree: &BTree) -> Vec<(Vec<u8>, Vec<u8>)> {
+    tree.iter().collect()
+}
+
+#[test]
+fn recover_rebuilds_root_after_master_is_zeroed() {
+    let db = TempDb::new();
+
+    let mut tree = db.open();
+    for i in 0..2000 {
+        tree.insert(&key(i), &val(i)).unwrap();
+    }
+    // overflow page 也要被当作存活的 page
+    tree.insert(b"big", &vec![7; 20_000]).unwrap();
+    for i in (0..2000).step_by(3) {
+        tree.delete(&key(i)).unwrap();
+    }
+    tree.commit().unwrap();
+    let expected = contents(&tree);
+    drop(tree);
+
+    zero_master(&db);
+    assert_eq!(
+        FileStore::open(&db.path).err(),
+        Some(BTreeError::CorruptPage)
+    );
+
+    let store = FileStore::recover(&db.path).unwrap();
+    let tree = BTree::with_store(Box::new(store));
+    assert_eq!(contents(&tree), expected);
+    tree.check().unwrap();
+    drop(tree);
+
+    // 恢复时重新写入了 master page，不能被根节点到达的 page 都进入了 free list
+    let store = FileStore::open(&db.path).unwrap();
+    let pages = store.page_count() as usize;
+    let free = store.free_count();
+    let mut tree = BTree::with_store(Box::new(store));
+    assert_eq!(contents(&tree), expected);
+    let overflow = 20_000_usize.div_ceil(BTREE_PAGE_SIZE - 16);
+    assert_eq!(1 + tree.check().unwrap().nodes + overflow + free, pages);
+
+    // 之后的修改复用 free list 中的 page
+    for i in 0..2000 {
+        tree.insert(&key(i), &val(i + 1)).unwrap();
+    }
+    tree.commit().unwrap();
+    drop(tree);
+    let tree = db.open();
+    assert_eq!(tree.get_value(&key(0)).unwrap(), Some(val(1)));
+}
+
+#[test]
+fn recover_after_several_commits() {
+    let db = TempDb::new();
+
+    let mut tree = db.open();
+    for round in 0..5 {
+        for i in 0..500 {
+            tree.insert(&key(i), &val(i + round)).unwrap();
+        }
+        for i in (round..500).step_by(7) {
+            tree.delete(&key(i)).unwrap();
+        }
+        tree.commit().unwrap();
+    }
+    let expected = contents(&tree);
+    drop(tree);
+
+    zero_master(&db);
+    let tree = BTree::with_store(Box::new(FileStore::recover(&db.path).unwrap()));
+    assert_eq!(contents(&tree), expected);
+}
+
+#[test]
+fn recover_keeps_index_trees() {
+    let db = TempDb::new();
+    // 按 value 中的编号建索引
+    let indexes = [Index::new("id", |val: &[u8]| Some(val[3..9].to_vec()))];
+
+    let mut tree = db.open();
+    for i in 0..300 {
+        tree.insert_indexed(&key(i), &val(i), &indexes).unwrap();
+    }
+    tree.commit().unwrap();
+    let expected = contents(&tree);
+    let ids: Vec<Vec<Vec<u8>>> = (0..300)
+        .map(|i| tree.index_scan("id", &val(i)[3..9]).unwrap())
+        .collect();
+    assert_eq!(ids[7], [key(7)]);
+    drop(tree);
+
+    // 索引树的根节点不是候选，索引的 page 也不能进入 free list
+    zero_master(&db);
+    let mut tree = BTree::with_store(Box::new(FileStore::recover(&db.path).unwrap()));
+    assert_eq!(contents(&tree), expected);
+    for (i, ids) in ids.iter().enumerate() {
+        assert_eq!(&tree.index_scan("id", &val(i as u32)[3..9]).unwrap(), ids);
+    }
+
+    // 之后的写入不会覆盖索引的 page
+    for i in 300..600 {
+        tree.insert(&key(i), &val(i)).unwrap();
+    }
+    tree.commit().unwrap();
+    for (i, ids) in ids.iter().enumerate() {
+        assert_eq!(&tree.index_scan("id", &val(i as u32)[3..9]).unwrap(), ids);
+    }
+    tree.check().unwrap();
+}
+
+#[test]
+fn recover_keeps_intact_master() {
+    let db = TempDb::new();
+
+    let mut tree = db.open();
+    tree.insert(b"key", b"value").unwrap();
+    tree.commit().unwrap();
+    let root = tree.root_ptr();
+    drop(tree);
+
+    let tree = BTree::with_store(Box::new(FileStore::recover(&db.path).unwrap()));
+    assert_eq!(tree.root_ptr(), root);
+    assert_eq!(tree.get_value(b"key").unwrap(), Some(b"value".to_vec()));
+}
+
+#[test]
+fn recover_fails_without_a_consistent_root() {
+    let db = TempDb::new();
+
+    let mut tree = db.open();
+    tree.insert(b"key", b"value").unwrap();
+    tree.commit().unwrap();
+    let root = tree.root_ptr();
+    drop(tree);
+
+    zero_master(&db);
+    let file = fs::OpenOptions::new().write(true).open(&db.path).unwrap();
+    file.write_all_at(&[0xff], root * BTREE_PAGE_SIZE as u64 + 100)
+        .unwrap();
+    drop(file);
+
+    assert_eq!(
+        FileStore::recover(&db.path).err(),
+        Some(BTreeError::CorruptPage)
+    );
+}
+
+#[test]
+fn updates_under_one_internal_node_do_not_leak() {
+    let db = TempDb::new();
+
+    let mut tree = db.open();
+    for i in 0..1000 {
+        tree.insert(&key(i), &val(i)).unwrap();
+    }
+    tree.commit().unwrap();
+    drop(tree);
+
+    let mut counts = vec![];
+    for round in 0..6 {
+        let store = FileStore::open(&db.path).unwrap();
+        let mut tree = BTree::with_store(Box::new(store));
+        // 反复更新同一个叶子节点中的 key，每次都替换从根节点到它的路径
+        for i in 0..200 {
+            tree.insert(&key(i % 20), &val(round * 1000 + i)).unwrap();
+        }
+        tree.commit().unwrap();
+        drop(tree);
+
+        // 每个 page 要么可以从根节点到达，要么在 free list 中
+        let store = FileStore::open(&db.path).unwrap();
+        let (pages, free) = (store.page_count(), store.free_count());
+        let tree = BTree::with_store(Box::new(store));
+        assert_eq!(1 + tree.check().unwrap().nodes + free, pages as usize);
+        counts.push(pages);
+    }
+    // 旧的子节点都被释放并复用，文件不再增长
+    assert_eq!(counts[2], counts[5], "{counts:?}");
+
+    let tree = db.open();
+    assert_eq!(tree.get_value(&key(19)).unwrap(), Some(val(5 * 1000 + 199)));
+}
+
+#[test]
+fn replaced_children_survive_until_commit() {
+    let db = TempDb::new();
+
+    let mut tree = db.open();
+    for i in 0..1000 {
+        tree.insert(&key(i), &val(i)).unwrap();
+    }
+    tree.commit().unwrap();
+
+    // 没有提交的更新释放的 page 仍然被已提交的树引用，不能被覆盖
+    for i in 0..1000 {
+        tree.insert(&key(i), &val(i + 1)).unwrap();
+    }
+    drop(tree);
+
+    let tree = db.open();
+    for i in 0..1000 {
+        assert_eq!(tree.get_value(&key(i)).unwrap(), Some(val(i)));
+    }
+    tree.check().unwrap();
+}
+
+#[test]
+fn per_op_durability_survives_crash() {
+    let db = TempDb::new();
+
+    let mut tree = db.open_with(Durability::PerOp);
+    for i in 0..50 {
+        tree.insert(&key(i), &val(i)).unwrap();
+        assert!(!tree.is_dirty());
+    }
+    for i in 0..10 {
+        assert!(tree.delete(&key(i)).unwrap());
+    }
+    tree.upsert(&key(20), b"", |old| [old, b"!"].concat())
+        .unwrap();
+    assert!(!tree.is_dirty());
+    // 模拟崩溃，不调用 commit 也不运行 drop
+    std::mem::forget(tree);
+
+    let tree = db.open();
+    assert_eq!(tree.len().unwrap(), 40);
+    for i in 0..50 {
+        let expected = match i {
+            0..10 => None,
+            20 => Some([val(20), b"!".to_vec()].concat()),
+            _ => Some(val(i)),
+        };
+        assert_eq!(tree.get_value(&key(i)).unwrap(), expected);
+    }
+    tree.check().unwrap();
+}
+
+#[test]
+fn per_op_durability_commits_with_transaction() {
+    let db = TempDb::new();
+
+    let mut tree = db.open_with(Durability::PerOp);
+    tree.insert(&key(0), &val(0)).unwrap();
+    let mut txn = tree.begin();
+    txn.insert(&key(1), &val(1)).unwrap();
+    txn.insert(&key(2), &val(2)).unwrap();
+    txn.rollback();
+    assert!(!tree.is_dirty());
+
+    let mut txn = tree.begin();
+    txn.insert(&key(3), &val(3)).unwrap();
+    txn.commit().unwrap();
+    std::mem::forget(tree);
+
+    let tree = db.open();
+    let keys: Vec<_> = tree.iter().map(|(k, _)| k).collect();
+    assert_eq!(keys, [key(0), key(3)]);
+}
+
+#[test]
+fn manual_durability_drop_behavior() {
+    let db = TempDb::new();
+
+    // Manual 时没有提交的修改在 drop 之后丢失
+    let mut tree = db.open();
+    tree.insert(&key(0), &val(0)).unwrap();
+    tree.commit().unwrap();
+    tree.insert(&key(1), &val(1)).unwrap();
+    assert!(tree.is_dirty());
+    drop(tree);
+    assert_eq!(db.open().len().unwrap(), 1);
+
+    // CommitOnDrop 时 drop 会提交
+    let mut tree = db.open_with(Durability::CommitOnDrop);
+    tree.insert(&key(1), &val(1)).unwrap();
+    assert!(tree.is_dirty());
+    drop(tree);
+    let tree = db.open();
+    assert!(!tree.is_dirty());
+    assert_eq!(tree.get_value(&key(1)).unwrap(), Some(val(1)));
+}
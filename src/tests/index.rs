@@ -0,0 +1,235 @@
+use super::{file_store::TempDb, shared_store::SharedStore};
+use crate::storage::{b_tree::BTree, error::BTreeError, index::Index};
+
+const NAMES: [&str; 5] = ["alice", "bob", "carol", "dave", "eve"];
+
+fn id(i: u32) -> Vec<u8> {
+    format!("id:{:04}", i).into_bytes()
+}
+
+fn record(i: u32, name: &str) -> Vec<u8> {
+    format!("name={name};age={}", 20 + i % 50).into_bytes()
+}
+
+// 从 "name=...;age=..." 中提取 name
+fn indexes() -> Vec<Index> {
+    vec![Index::new("name", |val: &[u8]| {
+        let val = std::str::from_utf8(val).ok()?;
+        let name = val.strip_prefix("name=")?.split(';').next()?;
+        Some(name.as_bytes().to_vec())
+    })]
+}
+
+fn ids_with(n: u32, name: &str) -> Vec<Vec<u8>> {
+    (0..n)
+        .filter(|i| NAMES[*i as usize % NAMES.len()] == name)
+        .map(id)
+        .collect()
+}
+
+fn populate(tree: &mut BTree, n: u32) {
+    let indexes = indexes();
+    for i in (0..n).rev() {
+        let name = NAMES[i as usize % NAMES.len()];
+        tree.insert_indexed(&id(i), &record(i, name), &indexes)
+            .unwrap();
+    }
+}
+
+#[test]
+fn index_scan_finds_ids_by_name() {
+    let mut tree = BTree::with_store(Box::new(SharedStore::default()));
+    populate(&mut tree, 300);
+    tree.check().unwrap();
+
+    for name in NAMES {
+        assert_eq!(
+            tree.index_scan("name", name.as_bytes()).unwrap(),
+            ids_with(300, name)
+        );
+    }
+    assert!(tree.index_scan("name", b"al").unwrap().is_empty());
+    assert!(tree.index_scan("name", b"mallory").unwrap().is_empty());
+    assert!(tree.index_scan("age", b"20").unwrap().is_empty());
+
+    // 索引项对主树不可见
+    assert_eq!(tree.len().unwrap(), 300);
+    assert_eq!(tree.iter().count(), 300);
+}
+
+#[test]
+fn update_and_delete_maintain_index() {
+    let store = SharedStore::default();
+    let mut tree = BTree::with_store(Box::new(store.clone()));
+    let indexes = indexes();
+    populate(&mut tree, 100);
+
+    // 改名之后只能通过新的名字找到
+    tree.insert_indexed(&id(0), &record(0, "mallory"), &indexes)
+        .unwrap();
+    assert_eq!(tree.index_scan("name", b"mallory").unwrap(), [id(0)]);
+    assert_eq!(
+        tree.index_scan("name", b"alice").unwrap(),
+        ids_with(100, "alice")[1..]
+    );
+
+    // 没有 name 的记录不进入索引
+    tree.insert_indexed(&id(5), b"anonymous", &indexes).unwrap();
+    assert_eq!(
+        tree.index_scan("name", b"alice").unwrap(),
+        ids_with(100, "alice")[2..]
+    );
+
+    assert!(tree.delete_indexed(&id(0), &indexes).unwrap());
+    assert!(!tree.delete_indexed(&id(0), &indexes).unwrap());
+    assert!(tree.index_scan("name", b"mallory").unwrap().is_empty());
+    for i in 1..50 {
+        assert!(tree.delete_indexed(&id(i), &indexes).unwrap());
+    }
+    let bob: Vec<_> = ids_with(100, "bob")
+        .into_iter()
+        .filter(|key| key >= &id(50))
+        .collect();
+    assert_eq!(tree.index_scan("name", b"bob").unwrap(), bob);
+    tree.check().unwrap();
+
+    // 全部删除之后，索引占用的 page 也都被释放
+    for i in 50..100 {
+        assert!(tree.delete_indexed(&id(i), &indexes).unwrap());
+    }
+    assert!(tree.is_empty());
+    assert_eq!(store.len(), 0);
+}
+
+#[test]
+fn invalid_index_entry_leaves_tree_unchanged() {
+    let mut tree = BTree::with_store(Box::new(SharedStore::default()));
+    let indexes = indexes();
+    populate(&mut tree, 10);
+    let long_name = "x".repeat(tree.config().max_key_size);
+
+    // 新记录的索引项过长，主树里也不应出现
+    assert_eq!(
+        tree.insert_indexed(&id(100), &record(100, &long_name), &indexes),
+        Err(BTreeError::KeyTooLong)
+    );
+    assert_eq!(tree.get_value(&id(100)).unwrap(), None);
+
+    // 更新失败时保留旧的记录和索引项
+    assert_eq!(
+        tree.insert_indexed(&id(0), &record(0, &long_name), &indexes),
+        Err(BTreeError::KeyTooLong)
+    );
+    assert_eq!(tree.get_value(&id(0)).unwrap(), Some(record(0, "alice")));
+    for name in NAMES {
+        assert_eq!(
+            tree.index_scan("name", name.as_bytes()).unwrap(),
+            ids_with(10, name)
+        );
+    }
+    assert_eq!(tree.len().unwrap(), 10);
+    tree.check().unwrap();
+}
+
+#[test]
+fn catalog_overflow_leaves_tree_unchanged() {
+    let store = SharedStore::default();
+    let mut tree = BTree::with_store(Box::new(store.clone()));
+    // 每个目录项 2 + 500 + 8 字节，第 6 个索引放不进哨兵
+    let long_indexes: Vec<Index> = (0..6)
+        .map(|i| Index::new(format!("{i}").repeat(500), |val: &[u8]| Some(val.to_vec())))
+        .collect();
+    tree.insert_indexed(&id(0), b"v0", &long_indexes[..5])
+        .unwrap();
+    let pages = store.len();
+
+    assert_eq!(
+        tree.insert_indexed(&id(1), b"v1", &long_indexes),
+        Err(BTreeError::ValueTooLong)
+    );
+    assert_eq!(tree.get_value(&id(1)).unwrap(), None);
+
+    // 更新时也不修改旧的记录和索引
+    assert_eq!(
+        tree.insert_indexed(&id(0), b"v1", &long_indexes),
+        Err(BTreeError::ValueTooLong)
+    );
+    assert_eq!(tree.get_value(&id(0)).unwrap(), Some(b"v0".to_vec()));
+    for index in &long_indexes[..5] {
+        assert_eq!(tree.index_scan(index.name(), b"v0").unwrap(), [id(0)]);
+        assert!(tree.index_scan(index.name(), b"v1").unwrap().is_empty());
+    }
+    assert_eq!(store.len(), pages);
+    tree.check().unwrap();
+}
+
+#[test]
+fn index_follows_transaction_and_commit() {
+    let db = TempDb::new();
+    let indexes = indexes();
+
+    let mut tree = db.open();
+    populate(&mut tree, 50);
+    tree.commit().unwrap();
+
+    let mut txn = tree.begin();
+    txn.insert_indexed(&id(100), &record(100, "mallory"), &indexes)
+        .unwrap();
+    assert!(txn.delete_indexed(&id(0), &indexes).unwrap());
+    assert_eq!(txn.index_scan("name", b"mallory").unwrap(), [id(100)]);
+    txn.rollback();
+
+    assert!(tree.index_scan("name", b"mallory").unwrap().is_empty());
+    assert_eq!(
+        tree.index_scan("name", b"alice").unwrap(),
+        ids_with(50, "alice")
+    );
+    drop(tree);
+
+    let tree = db.open();
+    for name in NAMES {
+        assert_eq!(
+            tree.index_scan("name", name.as_bytes()).unwrap(),
+            ids_with(50, name)
+        );
+    }
+}
+
+#[test]
+fn clear_frees_index_pages() {
+    let store = SharedStore::default();
+    let mut tree = BTree::with_store(Box::new(store.clone()));
+    populate(&mut tree, 200);
+    tree.clear().unwrap();
+    assert_eq!(store.len(), 0);
+    assert!(tree.index_scan("name", b"alice").unwrap().is_empty());
+}
+
+#[test]
+fn tree_with_only_catalog_is_empty() {
+    let store = SharedStore::default();
+    let mut tree = BTree::with_store(Box::new(store.clone()));
+    populate(&mut tree, 20);
+    // 不通过 delete_indexed 删除时索引项仍然存在，哨兵中保留着目录
+    for i in 0..20 {
+        assert!(tree.delete(&id(i)).unwrap());
+    }
+    assert_ne!(tree.root_ptr(), 0);
+    assert_eq!(tree.len().unwrap(), 0);
+    assert!(tree.is_empty());
+
+    // bulk_load 接受这样的空树，并且保留目录
+    tree.bulk_load((100..200).map(|i| (id(i), record(i, "zed"))))
+        .unwrap();
+    assert_eq!(tree.len().unwrap(), 100);
+    assert!(!tree.is_empty());
+    assert_eq!(
+        tree.index_scan("name", b"alice").unwrap(),
+        ids_with(20, "alice")
+    );
+    tree.check().unwrap();
+
+    // 旧的根节点已经释放，clear 之后没有遗留的 page
+    tree.clear().unwrap();
+    assert_eq!(store.len(), 0);
+}
@@ -0,0 +1,45 @@
+use rand::Rng;
+
+use crate::storage::key_codec::{decode_i64, decode_u64, encode_i64, encode_u64};
+
+#[test]
+fn u64_order_matches_numeric_order() {
+    let mut rng = rand::thread_rng();
+    for _ in 0..10_000 {
+        // 一半的样本取较小的值，覆盖高位字节相同的情况
+        let (a, b): (u64, u64) = if rng.gen() {
+            (rng.gen(), rng.gen())
+        } else {
+            (rng.gen_range(0..1024), rng.gen_range(0..1024))
+        };
+        assert_eq!(encode_u64(a).cmp(&encode_u64(b)), a.cmp(&b), "{a} {b}");
+        assert_eq!(decode_u64(encode_u64(a)), a);
+    }
+
+    for v in [0, 1, 255, 256, u64::MAX - 1, u64::MAX] {
+        assert_eq!(decode_u64(encode_u64(v)), v);
+    }
+    assert!(encode_u64(255) < encode_u64(256));
+}
+
+#[test]
+fn i64_order_matches_numeric_order() {
+    let mut rng = rand::thread_rng();
+    for _ in 0..10_000 {
+        let (a, b): (i64, i64) = if rng.gen() {
+            (rng.gen(), rng.gen())
+        } else {
+            (rng.gen_range(-1024..1024), rng.gen_range(-1024..1024))
+        };
+        assert_eq!(encode_i64(a).cmp(&encode_i64(b)), a.cmp(&b), "{a} {b}");
+        assert_eq!(decode_i64(encode_i64(a)), a);
+    }
+
+    let values = [i64::MIN, i64::MIN + 1, -256, -1, 0, 1, 255, i64::MAX];
+    for pair in values.windows(2) {
+        assert!(encode_i64(pair[0]) < encode_i64(pair[1]));
+    }
+    for v in values {
+        assert_eq!(decode_i64(encode_i64(v)), v);
+    }
+}
@@ -1,3 +1,62 @@
+#[cfg(test)]
+mod alloc_counter;
+#[cfg(test)]
+mod arena;
+#[cfg(test)]
+mod atomic_file;
+#[cfg(test)]
+mod b_tree;
+#[cfg(test)]
+mod b_tree_api;
+#[cfg(test)]
+mod b_tree_delete;
+#[cfg(test)]
+mod bulk;
+#[cfg(test)]
+mod cached_store;
+#[cfg(test)]
+mod check;
+#[cfg(test)]
+mod checksum;
+#[cfg(test)]
+mod codec;
+#[cfg(test)]
+mod comparator;
+#[cfg(test)]
+mod config;
+#[cfg(test)]
+mod cursor;
+#[cfg(test)]
+mod decoded;
+#[cfg(test)]
+mod dump;
+#[cfg(test)]
+mod file_store;
+#[cfg(test)]
+mod index;
+#[cfg(test)]
+mod key_codec;
+#[cfg(test)]
+mod overflow;
+#[cfg(test)]
+mod page_store;
+#[cfg(test)]
+mod scan;
+#[cfg(test)]
+mod shared;
+#[cfg(test)]
+mod shared_store;
+#[cfg(test)]
+mod snapshot;
+#[cfg(test)]
+mod stats;
+#[cfg(test)]
+mod transaction;
+#[cfg(test)]
+mod util;
+#[cfg(test)]
+mod wal;
+
 #[cfg(test)]
 pub mod test {
     use std::{
@@ -6,57 +65,33 @@ pub mod test {
         path::PathBuf,
     };
 
-    use rand::Rng;
+    use crate::storage::atomic_file::{tmp_path, write_atomic};
 
-    type result<T> = Result<T, Error>;
+    type Result<T> = std::result::Result<T, Error>;
 
-    pub fn save_data_1(path: PathBuf, data: &[u8]) -> result<()> {
+    pub fn save_data_1(path: PathBuf, data: &[u8]) -> Result<()> {
         let mut fp = File::create(path)?;
         fp.write_all(data)?;
 
         Ok(())
     }
 
-    pub fn save_data_2(path: PathBuf, data: &[u8]) -> result<()> {
-        let mut rng = rand::thread_rng();
-        let random_int = rng.gen_range(0..i32::MAX);
-
-        let tmp = format!("{}.tmp.{random_int}", path.to_string_lossy().to_string());
+    // 写入临时文件之后 rename，没有 fsync，掉电时仍可能丢失内容
+    pub fn save_data_2(path: PathBuf, data: &[u8]) -> Result<()> {
+        let tmp = tmp_path(&path);
 
-        let mut fp = File::create(&path)?;
-        match fp.write_all(data) {
-            Ok(_) => {
-                fs::rename(tmp, &path)?;
+        let result = File::create(&tmp).and_then(|mut fp| fp.write_all(data));
+        match result.and_then(|_| fs::rename(&tmp, &path)) {
+            Ok(_) => Ok(()),
+            Err(err) => {
+                let _ = fs::remove_file(&tmp);
+                Err(err)
             }
-            Err(_) => {
-                fs::remove_file(tmp)?;
-            }
-        };
-
-        Ok(())
+        }
     }
 
-    pub fn save_data_3(path: PathBuf, data: &[u8]) -> result<()> {
-        let mut rng = rand::thread_rng();
-        let random_int = rng.gen_range(0..i32::MAX);
-
-        let tmp = format!("{}.tmp.{random_int}", path.to_string_lossy().to_string());
-
-        let mut fp = File::create(&path)?;
-        match fp.write_all(data) {
-            Ok(_) => match fp.sync_all() {
-                Ok(_) => {
-                    fs::rename(tmp, &path)?;
-                }
-                Err(_) => {
-                    fs::remove_file(tmp)?;
-                }
-            },
-            Err(_) => {
-                fs::remove_file(tmp)?;
-            }
-        };
-
-        Ok(())
+    // rename 之前先 fsync 临时文件
+    pub fn save_data_3(path: PathBuf, data: &[u8]) -> Result<()> {
+        write_atomic(&path, |fp| fp.write_all(data))
     }
 }
@@ -0,0 +1,115 @@
+use std::ops::Bound;
+
+use super::{shared_store::SharedStore, util::new_tree};
+use crate::storage::{
+    b_tree::{BNode, BTree, BTreeConfig, NodeType, BTREE_PAGE_SIZE},
+    error::BTreeError,
+    page_store::MemoryStore,
+};
+
+fn blob(len: usize, seed: u8) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8 ^ seed).collect()
+}
+
+#[test]
+fn large_value_round_trip_and_free() {
+    let store = SharedStore::default();
+    let mut tree = BTree::with_store(Box::new(store.clone()));
+    for i in 0..10_u8 {
+        tree.insert(&[b'k', i], &[i; 100]).unwrap();
+    }
+    let pages = store.len();
+
+    let value = blob(1 << 20, 7);
+    tree.insert(b"k\x05blob", &value).unwrap();
+    // 1 MB 需要约 257 个 overflow page
+    assert!(store.len() > pages + 250);
+    assert_eq!(tree.get_value(b"k\x05blob").unwrap(), Some(value.clone()));
+
+    // overflow value 对 scan 可见
+    let items: Vec<_> = tree.iter().collect();
+    assert_eq!(items.len(), 11);
+    assert_eq!(items[6], (b"k\x05blob".to_vec(), value));
+
+    assert!(tree.delete(b"k\x05blob").unwrap());
+    assert_eq!(tree.get_value(b"k\x05blob").unwrap(), None);
+    assert_eq!(store.len(), pages);
+
+    for i in 0..10_u8 {
+        assert_eq!(tree.get_value(&[b'k', i]).unwrap(), Some(vec![i; 100]));
+    }
+}
+
+#[test]
+fn overwriting_large_value_frees_old_pages() {
+    let store = SharedStore::default();
+    let mut tree = BTree::with_store(Box::new(store.clone()));
+
+    tree.insert(b"key", &blob(100_000, 1)).unwrap();
+    let pages = store.len();
+
+    // 覆盖为同样大小的 value，page 数量不变
+    tree.insert(b"key", &blob(100_000, 2)).unwrap();
+    assert_eq!(store.len(), pages);
+    assert_eq!(tree.get_value(b"key").unwrap(), Some(blob(100_000, 2)));
+
+    // 覆盖为内联的 value，overflow page 全部释放
+    tree.insert(b"key", b"small").unwrap();
+    assert_eq!(store.len(), 1);
+    assert_eq!(tree.get_value(b"key").unwrap(), Some(b"small".to_vec()));
+
+    tree.insert(b"key", &blob(5000, 3)).unwrap();
+    assert_eq!(tree.get_value(b"key").unwrap(), Some(blob(5000, 3)));
+    assert!(tree.delete(b"key").unwrap());
+    assert_eq!(store.len(), 0);
+}
+
+#[test]
+fn overflow_with_small_pages() {
+    let config = BTreeConfig {
+        page_size: 256,
+        max_key_size: 16,
+        max_val_size: 64,
+        max_blob_size: 4096,
+        ..BTreeConfig::default()
+    };
+    let store = Box::new(MemoryStore::with_page_size(config.page_size));
+    let mut tree = BTree::with_config(store, config).unwrap();
+
+    for i in 0..100_u32 {
+        let len = (i as usize * 37) % 4096;
+        tree.insert(format!("{:04}", i).as_bytes(), &blob(len, i as u8))
+            .unwrap();
+    }
+
+    let start = b"0050".as_slice();
+    let items: Vec<_> = tree
+        .scan(Bound::Included(start), Bound::Unbounded)
+        .collect();
+    assert_eq!(items.len(), 50);
+    for (i, (key, val)) in (50..100_u32).zip(items) {
+        assert_eq!(key, format!("{:04}", i).into_bytes());
+        assert_eq!(val, blob((i as usize * 37) % 4096, i as u8));
+    }
+}
+
+#[test]
+fn corrupt_overflow_length_is_rejected() {
+    let mut tree = new_tree();
+    tree.insert(b"big", &blob(20_000, 1)).unwrap();
+
+    // 引用中的长度被改成一个巨大的值，读取时不能按它分配内存
+    let mut reference = u64::MAX.to_le_bytes().to_vec();
+    reference.extend_from_slice(&1_u64.to_le_bytes());
+    let mut leaf = BNode::new(BTREE_PAGE_SIZE);
+    leaf.set_header(NodeType::Leaf as u16, 2);
+    leaf.node_append_kv(0, 0, vec![], vec![]).unwrap();
+    leaf.node_append_kv(1, 0, b"big".to_vec(), reference)
+        .unwrap();
+    leaf.set_overflow(1);
+    let root = tree.new(&leaf).unwrap();
+    tree.set_root(root);
+
+    assert_eq!(tree.get_value(b"big"), Err(BTreeError::CorruptPage));
+    assert_eq!(tree.delete(b"big"), Err(BTreeError::CorruptPage));
+}
@@ -0,0 +1,129 @@
+use super::util::{key, new_tree};
+use crate::storage::{
+    b_tree::{BNode, BTree, NodeType, BTREE_PAGE_SIZE},
+    page_store::{MemoryStore, PageStore, StoreStats},
+};
+
+fn leaf(key: &[u8], val: &[u8]) -> BNode {
+    let mut node = BNode::new(BTREE_PAGE_SIZE);
+    node.set_header(NodeType::Leaf as u16, 1);
+    node.node_append_kv(0, 0, key.to_vec(), val.to_vec())
+        .unwrap();
+    node
+}
+
+#[test]
+fn memory_store_alloc_get_free() {
+    let mut store = MemoryStore::new();
+
+    let ptrs: Vec<u64> = (0..3_u8)
+        .map(|i| store.alloc(&leaf(&[b'k', i], &[b'v', i])).unwrap())
+        .collect();
+    assert_eq!(ptrs, vec![1, 2, 3]);
+    assert_eq!(store.len(), 3);
+
+    for (i, ptr) in ptrs.iter().enumerate() {
+        let node = store.get(*ptr).unwrap();
+        assert_eq!(node.nkeys(), 1);
+        assert_eq!(node.get_key(0), vec![b'k', i as u8]);
+        assert_eq!(node.get_val(0), vec![b'v', i as u8]);
+    }
+
+    store.free(ptrs[1]);
+    assert_eq!(store.len(), 2);
+
+    // 指针单调递增，不会复用已释放的 page
+    let ptr = store.alloc(&leaf(b"new", b"page")).unwrap();
+    assert_eq!(ptr, 4);
+    assert_eq!(store.get(ptr).unwrap().get_key(0), b"new");
+    assert_eq!(store.get(ptrs[2]).unwrap().get_key(0), vec![b'k', 2]);
+}
+
+#[test]
+fn btree_delegates_to_store() {
+    let mut tree = new_tree();
+
+    let ptr = tree.new(&leaf(b"key", b"val")).unwrap();
+    assert_eq!(tree.get(ptr).unwrap().get_val(0), b"val");
+
+    tree.del(ptr);
+    let next = tree.new(&leaf(b"key2", b"val2")).unwrap();
+    assert!(next > ptr);
+}
+
+#[test]
+fn memory_store_counts_operations() {
+    let mut store = MemoryStore::new();
+    let a = store.alloc(&leaf(b"a", b"1")).unwrap();
+    let b = store.alloc(&leaf(b"b", b"2")).unwrap();
+    store.get(a).unwrap();
+    store.get(b).unwrap();
+    store.get(b).unwrap();
+    store.free(a);
+    // 读取失败也算一次
+    assert!(store.get(a).is_err());
+    assert_eq!(
+        store.stats(),
+        StoreStats {
+            gets: 4,
+            allocs: 2,
+            frees: 1
+        }
+    );
+
+    store.reset_stats();
+    assert_eq!(store.stats(), StoreStats::default());
+}
+
+// 20000 个 key 批量写入之后高度为 3
+fn height_3_tree() -> BTree {
+    let mut tree = new_tree();
+    tree.bulk_load((0..20_000).map(|i| (key(i), vec![1; 20])))
+        .unwrap();
+    assert_eq!(tree.stats().unwrap().height, 3);
+    tree
+}
+
+#[test]
+fn point_lookup_reads_one_page_per_level() {
+    let tree = height_3_tree();
+
+    for i in [0, 7777, 19_999] {
+        tree.reset_store_stats();
+        assert!(tree.get_value(&key(i)).unwrap().is_some());
+        assert_eq!(tree.store_stats().gets, 3);
+    }
+    tree.reset_store_stats();
+    assert_eq!(tree.get_value(b"missing").unwrap(), None);
+    assert_eq!(tree.store_stats().gets, 3);
+    assert_eq!(tree.store_stats().allocs, 0);
+}
+
+#[test]
+fn get_batch_reads_fewer_pages_than_single_lookups() {
+    let tree = height_3_tree();
+    let keys: Vec<Vec<u8>> = (0..1000).map(|i| key(i * 7)).collect();
+    let refs: Vec<&[u8]> = keys.iter().map(|key| key.as_slice()).collect();
+
+    tree.reset_store_stats();
+    let batch = tree.get_batch(&refs).unwrap();
+    let batch_gets = tree.store_stats().gets;
+    assert!(batch.iter().all(|val| val.is_some()));
+
+    // 单独查找时每个 key 都从根节点读起
+    assert!(batch_gets < refs.len() as u64, "{batch_gets}");
+    assert!(batch_gets * 10 < 3 * refs.len() as u64, "{batch_gets}");
+}
+
+#[test]
+fn updates_count_allocs_and_frees() {
+    let mut tree = height_3_tree();
+    tree.reset_store_stats();
+    tree.insert(&key(5), b"new").unwrap();
+
+    // 复制从根节点到叶子节点的路径，旧的路径被释放
+    let stats = tree.store_stats();
+    assert_eq!(stats.gets, 3);
+    assert_eq!(stats.allocs, 3);
+    assert_eq!(stats.frees, 3);
+}
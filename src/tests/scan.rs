@@ -0,0 +1,293 @@
+use std::ops::Bound;
+
+use rand::seq::SliceRandom;
+
+use super::{shared_store::SharedStore, util::new_tree};
+use crate::storage::{
+    b_tree::BTree,
+    scan::{prefix_end, ScanIter},
+};
+
+fn key(i: u32) -> Vec<u8> {
+    format!("k{:02}", i).into_bytes()
+}
+
+fn populated(n: u32) -> BTree {
+    let mut tree = new_tree();
+    for i in 0..n {
+        // 较大的 value 让树有多层
+        tree.insert(&key(i), &vec![i as u8; 500]).unwrap();
+    }
+    tree
+}
+
+fn assert_increasing(keys: &[Vec<u8>]) {
+    for pair in keys.windows(2) {
+        assert!(pair[0] < pair[1]);
+    }
+}
+
+#[test]
+fn scan_full() {
+    let tree = populated(100);
+    let items: Vec<_> = tree.scan(Bound::Unbounded, Bound::Unbounded).collect();
+    let keys: Vec<_> = items.iter().map(|(k, _)| k.clone()).collect();
+
+    assert_eq!(keys, (0..100).map(key).collect::<Vec<_>>());
+    assert_increasing(&keys);
+    for (i, (_, v)) in items.iter().enumerate() {
+        assert_eq!(*v, vec![i as u8; 500]);
+    }
+}
+
+#[test]
+fn scan_bounded() {
+    let tree = populated(100);
+    let keys: Vec<_> = tree
+        .scan(Bound::Included(b"k10"), Bound::Excluded(b"k20"))
+        .map(|(k, _)| k)
+        .collect();
+    assert_eq!(keys, (10..20).map(key).collect::<Vec<_>>());
+    assert_increasing(&keys);
+
+    let keys: Vec<_> = tree
+        .scan(Bound::Excluded(b"k10"), Bound::Included(b"k20"))
+        .map(|(k, _)| k)
+        .collect();
+    assert_eq!(keys, (11..21).map(key).collect::<Vec<_>>());
+
+    // 边界不是已有的 key
+    let keys: Vec<_> = tree
+        .scan(Bound::Included(b"k105"), Bound::Unbounded)
+        .map(|(k, _)| k)
+        .collect();
+    assert_eq!(keys, (11..100).map(key).collect::<Vec<_>>());
+
+    let keys: Vec<_> = tree
+        .scan(Bound::Unbounded, Bound::Excluded(b"k03"))
+        .map(|(k, _)| k)
+        .collect();
+    assert_eq!(keys, (0..3).map(key).collect::<Vec<_>>());
+}
+
+#[test]
+fn scan_empty_range() {
+    let tree = populated(100);
+    assert_eq!(
+        tree.scan(Bound::Included(b"k20"), Bound::Excluded(b"k20"))
+            .count(),
+        0
+    );
+    assert_eq!(
+        tree.scan(Bound::Included(b"k30"), Bound::Included(b"k20"))
+            .count(),
+        0
+    );
+    assert_eq!(
+        tree.scan(Bound::Excluded(b"k99"), Bound::Unbounded).count(),
+        0
+    );
+    assert_eq!(
+        tree.scan(Bound::Unbounded, Bound::Excluded(b"k")).count(),
+        0
+    );
+
+    let empty = populated(0);
+    assert_eq!(empty.scan(Bound::Unbounded, Bound::Unbounded).count(), 0);
+}
+
+#[test]
+fn iter_and_len() {
+    let mut tree = new_tree();
+    assert_eq!(tree.len().unwrap(), 0);
+    assert!(tree.is_empty());
+    assert_eq!(tree.iter().count(), 0);
+
+    // 随机顺序插入
+    let mut ids: Vec<u32> = (0..300).collect();
+    ids.shuffle(&mut rand::thread_rng());
+    for i in &ids {
+        tree.insert(&key(*i), &[*i as u8; 200]).unwrap();
+    }
+    assert_eq!(tree.len().unwrap(), 300);
+    assert!(!tree.is_empty());
+
+    let items: Vec<_> = tree.iter().collect();
+    let keys: Vec<_> = items.iter().map(|(k, _)| k.clone()).collect();
+    assert_increasing(&keys);
+    let mut expected: Vec<_> = (0..300).map(key).collect();
+    expected.sort();
+    assert_eq!(keys, expected);
+
+    for i in &ids[..100] {
+        assert!(tree.delete(&key(*i)).unwrap());
+    }
+    assert_eq!(tree.len().unwrap(), 200);
+
+    let items: Vec<_> = tree.iter().collect();
+    let keys: Vec<_> = items.iter().map(|(k, _)| k.clone()).collect();
+    assert_increasing(&keys);
+    let mut expected: Vec<_> = ids[100..].iter().map(|i| key(*i)).collect();
+    expected.sort();
+    assert_eq!(keys, expected);
+    for (k, v) in &items {
+        let i: u32 = std::str::from_utf8(&k[1..]).unwrap().parse().unwrap();
+        assert_eq!(v, &vec![i as u8; 200]);
+    }
+}
+
+#[test]
+fn min_and_max() {
+    let mut tree = new_tree();
+    assert_eq!(tree.min().unwrap(), None);
+    assert_eq!(tree.max().unwrap(), None);
+
+    tree.insert(b"only", b"one").unwrap();
+    let only = Some((b"only".to_vec(), b"one".to_vec()));
+    assert_eq!(tree.min().unwrap(), only);
+    assert_eq!(tree.max().unwrap(), only);
+
+    let mut ids: Vec<u32> = (0..300).collect();
+    ids.shuffle(&mut rand::thread_rng());
+    for i in &ids {
+        tree.insert(&key(*i), &[*i as u8; 200]).unwrap();
+    }
+
+    let items: Vec<_> = tree.iter().collect();
+    assert_eq!(tree.min().unwrap().as_ref(), items.first());
+    assert_eq!(tree.max().unwrap().as_ref(), items.last());
+    assert_eq!(tree.max().unwrap().unwrap().0, b"only");
+}
+
+#[test]
+fn iter_rev_matches_reversed_iter() {
+    let tree = new_tree();
+    assert_eq!(tree.iter_rev().count(), 0);
+
+    let tree = populated(100);
+    let mut forward: Vec<_> = tree.iter().collect();
+    forward.reverse();
+    let backward: Vec<_> = tree.iter_rev().collect();
+    assert_eq!(backward, forward);
+    assert_eq!(backward.len(), 100);
+
+    let keys: Vec<_> = backward.into_iter().map(|(k, _)| k).rev().collect();
+    assert_increasing(&keys);
+}
+
+#[test]
+fn reverse_scan_respects_bounds() {
+    let tree = populated(100);
+    let rev = |start: Bound<&[u8]>, end: Bound<&[u8]>| -> Vec<Vec<u8>> {
+        ScanIter::new(&tree, tree.root_ptr(), start, end, true)
+            .map(|(k, _)| k)
+            .collect()
+    };
+
+    let (k10, k20) = (key(10), key(20));
+    assert_eq!(
+        rev(Bound::Included(&k10), Bound::Included(&k20)),
+        (10..=20).rev().map(key).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        rev(Bound::Excluded(&k10), Bound::Excluded(&k20)),
+        (11..20).rev().map(key).collect::<Vec<_>>()
+    );
+    // 边界不是已有的 key
+    assert_eq!(
+        rev(Bound::Included(b"k105"), Bound::Included(b"k205")),
+        (11..=20).rev().map(key).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        rev(Bound::Unbounded, Bound::Excluded(b"k00")),
+        Vec::<Vec<u8>>::new()
+    );
+    assert_eq!(rev(Bound::Unbounded, Bound::Included(b"k00")), vec![key(0)]);
+}
+
+#[test]
+fn scan_prefix_only_returns_matching_keys() {
+    let mut tree = new_tree();
+    for key in [
+        &b"user:1"[..],
+        b"user:2",
+        b"usr:1",
+        b"user",
+        b"user;",
+        b"\xff",
+        b"\xff\xff",
+        b"\xff\xff\x01",
+    ] {
+        tree.insert(key, key).unwrap();
+    }
+    let keys =
+        |prefix: &[u8]| -> Vec<Vec<u8>> { tree.scan_prefix(prefix).map(|(key, _)| key).collect() };
+
+    assert_eq!(keys(b"user:"), [b"user:1".to_vec(), b"user:2".to_vec()]);
+    assert_eq!(keys(b"us").len(), 5);
+    assert_eq!(keys(b"user:3"), Vec::<Vec<u8>>::new());
+    // 全是 0xff 的前缀没有上界
+    assert_eq!(
+        keys(b"\xff\xff"),
+        [b"\xff\xff".to_vec(), b"\xff\xff\x01".to_vec()]
+    );
+    // 空前缀返回所有 key
+    assert_eq!(
+        keys(b""),
+        tree.iter().map(|(key, _)| key).collect::<Vec<_>>()
+    );
+    assert_eq!(keys(b"").len(), 8);
+}
+
+#[test]
+fn prefix_end_skips_trailing_ff() {
+    assert_eq!(prefix_end(b"user:"), Some(b"user;".to_vec()));
+    assert_eq!(prefix_end(b"a\xff\xff"), Some(b"b".to_vec()));
+    assert_eq!(prefix_end(b"\x01\xff"), Some(b"\x02".to_vec()));
+    assert_eq!(prefix_end(b"\xff\xff"), None);
+    assert_eq!(prefix_end(b""), None);
+}
+
+#[test]
+fn get_range_bounded_fetch() {
+    let tree = populated(100);
+
+    let items = tree.get_range(&key(10), &key(50), 15).unwrap();
+    let keys: Vec<Vec<u8>> = items.iter().map(|(k, _)| k.clone()).collect();
+    assert_eq!(keys, (10..25).map(key).collect::<Vec<_>>());
+    assert_eq!(items[0].1, vec![10; 500]);
+
+    // 范围内的 key 不足 limit 个，end 不包含在内
+    let items = tree.get_range(&key(90), &key(99), 100).unwrap();
+    let keys: Vec<Vec<u8>> = items.into_iter().map(|(k, _)| k).collect();
+    assert_eq!(keys, (90..99).map(key).collect::<Vec<_>>());
+    assert_increasing(&keys);
+}
+
+#[test]
+fn get_range_empty_cases() {
+    let tree = populated(100);
+    assert!(tree.get_range(&key(50), &key(10), 10).unwrap().is_empty());
+    assert!(tree.get_range(&key(10), &key(10), 10).unwrap().is_empty());
+    assert!(tree.get_range(&key(10), &key(50), 0).unwrap().is_empty());
+    assert!(tree.get_range(b"x", b"y", 10).unwrap().is_empty());
+
+    let empty = new_tree();
+    assert!(empty.get_range(b"a", b"z", 10).unwrap().is_empty());
+}
+
+#[test]
+fn get_range_stops_at_limit() {
+    let store = SharedStore::default();
+    let mut tree = BTree::with_store(Box::new(store.clone()));
+    for i in 0..100 {
+        tree.insert(&key(i), &vec![i as u8; 500]).unwrap();
+    }
+    let nodes = tree.check().unwrap().nodes;
+
+    let before = store.reads();
+    let items = tree.get_range(&key(0), &key(99), 3).unwrap();
+    assert_eq!(items.len(), 3);
+    // 只读取到第一个叶子节点为止，不遍历整棵树
+    assert!(store.reads() - before < nodes / 2);
+}
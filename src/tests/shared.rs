@@ -0,0 +1,86 @@
+use std::{
+    ops::Bound,
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+};
+
+use super::util::{key, new_tree};
+use crate::storage::shared::SharedBTree;
+
+const KEYS: u32 = 50;
+const ROUNDS: u32 = 100;
+
+fn val(round: u32) -> Vec<u8> {
+    format!("round{:06}", round).repeat(10).into_bytes()
+}
+
+#[test]
+fn readers_see_consistent_tree() {
+    let tree = SharedBTree::new(new_tree());
+    tree.write(|tree| {
+        for i in 0..KEYS {
+            tree.insert(&key(i), &val(0)).unwrap();
+        }
+    });
+
+    let done = AtomicBool::new(false);
+    thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                let mut last = 0;
+                while !done.load(Ordering::Acquire) {
+                    // 同一次读取中所有 key 都来自同一轮写入
+                    let values: Vec<_> = tree.read(|tree| {
+                        tree.scan(Bound::Unbounded, Bound::Unbounded)
+                            .map(|(_, v)| v)
+                            .collect()
+                    });
+                    assert_eq!(values.len(), KEYS as usize);
+                    assert!(values.iter().all(|v| v == &values[0]));
+
+                    let round: u32 = std::str::from_utf8(&values[0][5..11])
+                        .unwrap()
+                        .parse()
+                        .unwrap();
+                    assert!(round >= last);
+                    assert_eq!(values[0], val(round));
+                    last = round;
+
+                    let single = tree.get_value(&key(round % KEYS)).unwrap().unwrap();
+                    assert!(single >= val(round));
+                }
+            });
+        }
+
+        scope.spawn(|| {
+            for round in 1..=ROUNDS {
+                tree.write(|tree| {
+                    for i in 0..KEYS {
+                        tree.insert(&key(i), &val(round)).unwrap();
+                    }
+                });
+            }
+            done.store(true, Ordering::Release);
+        });
+    });
+
+    for i in 0..KEYS {
+        assert_eq!(tree.get_value(&key(i)).unwrap(), Some(val(ROUNDS)));
+    }
+}
+
+#[test]
+fn single_operations() {
+    let tree = SharedBTree::new(new_tree());
+    let clone = tree.clone();
+    thread::spawn(move || {
+        clone.insert(b"k", b"v").unwrap();
+        clone.commit().unwrap();
+    })
+    .join()
+    .unwrap();
+
+    assert_eq!(tree.get_value(b"k").unwrap(), Some(b"v".to_vec()));
+    assert!(tree.delete(b"k").unwrap());
+    assert_eq!(tree.get_value(b"k").unwrap(), None);
+}
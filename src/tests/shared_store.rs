@@ -0,0 +1,59 @@
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+
+use crate::storage::{
+    b_tree::BNode,
+    error::BTreeError,
+    page_store::{MemoryStore, PageStore},
+};
+
+// 与测试共享的 MemoryStore，用于检查存活的 page 数量和读写次数
+#[derive(Clone, Default)]
+pub struct SharedStore {
+    store: Arc<Mutex<MemoryStore>>,
+    allocs: Arc<AtomicUsize>,
+    reads: Arc<AtomicUsize>,
+    full: Arc<AtomicBool>,
+}
+
+impl SharedStore {
+    pub fn len(&self) -> usize {
+        self.store.lock().unwrap().len()
+    }
+
+    // 分配 page 的次数
+    pub fn allocs(&self) -> usize {
+        self.allocs.load(Ordering::Relaxed)
+    }
+
+    // 读取 page 的次数
+    pub fn reads(&self) -> usize {
+        self.reads.load(Ordering::Relaxed)
+    }
+
+    // 设置之后分配 page 返回 IO 错误，用于模拟磁盘已满
+    pub fn set_full(&self, full: bool) {
+        self.full.store(full, Ordering::Relaxed)
+    }
+}
+
+impl PageStore for SharedStore {
+    fn get(&self, ptr: u64) -> Result<BNode, BTreeError> {
+        self.reads.fetch_add(1, Ordering::Relaxed);
+        self.store.lock().unwrap().get(ptr)
+    }
+
+    fn alloc(&mut self, node: &BNode) -> Result<u64, BTreeError> {
+        if self.full.load(Ordering::Relaxed) {
+            return Err(BTreeError::Io(std::io::ErrorKind::StorageFull));
+        }
+        self.allocs.fetch_add(1, Ordering::Relaxed);
+        self.store.lock().unwrap().alloc(node)
+    }
+
+    fn free(&mut self, ptr: u64) {
+        self.store.lock().unwrap().free(ptr)
+    }
+}
@@ -0,0 +1,219 @@
+use super::{file_store::TempDb, shared_store::SharedStore, util::key};
+use crate::storage::{b_tree::BTree, error::BTreeError};
+
+fn val(i: u32, version: u32) -> Vec<u8> {
+    format!("val{:04}-{}", i, version).repeat(10).into_bytes()
+}
+
+#[test]
+fn snapshot_sees_old_values() {
+    let store = SharedStore::default();
+    let mut tree = BTree::with_store(Box::new(store.clone()));
+    for i in 0..200 {
+        tree.insert(&key(i), &val(i, 0)).unwrap();
+    }
+    let pages = store.len();
+
+    let snapshot = tree.snapshot();
+    assert_eq!(snapshot.root_ptr(), tree.root_ptr());
+    for i in 0..100 {
+        tree.insert(&key(i), &val(i, 1)).unwrap();
+    }
+    for i in 100..200 {
+        assert!(tree.delete(&key(i)).unwrap());
+    }
+    tree.insert(&key(500), &val(500, 1)).unwrap();
+
+    for i in 0..200 {
+        assert_eq!(snapshot.get_value(&tree, &key(i)).unwrap(), Some(val(i, 0)));
+    }
+    assert_eq!(snapshot.get_value(&tree, &key(500)).unwrap(), None);
+    let items: Vec<_> = snapshot.iter(&tree).collect();
+    assert_eq!(items.len(), 200);
+    assert_eq!(items[150], (key(150), val(150, 0)));
+
+    for i in 0..100 {
+        assert_eq!(tree.get_value(&key(i)).unwrap(), Some(val(i, 1)));
+    }
+    assert_eq!(tree.get_value(&key(150)).unwrap(), None);
+    assert_eq!(tree.len().unwrap(), 101);
+
+    // snapshot 存活期间旧的 page 都没有被释放
+    assert!(store.len() > pages);
+    let live = store.len();
+
+    // snapshot 结束之后，下一次写入时释放
+    drop(snapshot);
+    tree.insert(&key(501), &val(501, 1)).unwrap();
+    assert!(store.len() < live);
+}
+
+#[test]
+fn pages_are_released_after_all_snapshots_drop() {
+    let store = SharedStore::default();
+    let mut tree = BTree::with_store(Box::new(store.clone()));
+    tree.insert(b"a", b"1").unwrap();
+
+    let first = tree.snapshot();
+    tree.insert(b"a", b"2").unwrap();
+    let second = tree.snapshot();
+    tree.insert(b"a", b"3").unwrap();
+    assert_eq!(store.len(), 3);
+
+    assert_eq!(first.get_value(&tree, b"a").unwrap(), Some(b"1".to_vec()));
+    assert_eq!(second.get_value(&tree, b"a").unwrap(), Some(b"2".to_vec()));
+
+    drop(first);
+    tree.insert(b"b", b"4").unwrap();
+    assert_eq!(second.get_value(&tree, b"a").unwrap(), Some(b"2".to_vec()));
+    assert_eq!(store.len(), 4);
+
+    drop(second);
+    tree.commit().unwrap();
+    assert_eq!(store.len(), 1);
+}
+
+#[test]
+fn snapshot_survives_commit_on_file_store() {
+    let db = TempDb::new();
+    let mut tree = db.open();
+    for i in 0..300 {
+        tree.insert(&key(i), &val(i, 0)).unwrap();
+    }
+    tree.commit().unwrap();
+
+    let snapshot = tree.snapshot();
+    // 多次提交之后，被 snapshot 引用的 page 也不会被复用
+    for round in 1..5 {
+        for i in 0..300 {
+            tree.insert(&key(i), &val(i, round)).unwrap();
+        }
+        tree.commit().unwrap();
+    }
+
+    for i in 0..300 {
+        assert_eq!(snapshot.get_value(&tree, &key(i)).unwrap(), Some(val(i, 0)));
+        assert_eq!(tree.get_value(&key(i)).unwrap(), Some(val(i, 4)));
+    }
+}
+
+#[test]
+fn reused_page_is_never_read_through_old_snapshot() {
+    let db = TempDb::new();
+    let mut tree = db.open();
+    for i in 0..300 {
+        tree.insert(&key(i), &val(i, 0)).unwrap();
+    }
+    tree.commit().unwrap();
+
+    let pinned = tree.snapshot();
+    let unpinned = tree.snapshot_unpinned();
+    let root = tree.root_ptr();
+
+    // 更新之后提交，没有被固定的旧 page 进入 free list，再次写入时被复用
+    for i in 0..300 {
+        tree.insert(&key(i), &val(i, 1)).unwrap();
+    }
+    tree.commit().unwrap();
+    drop(pinned);
+    for round in 2..4 {
+        for i in 0..300 {
+            tree.insert(&key(i), &val(i, round)).unwrap();
+        }
+        tree.commit().unwrap();
+    }
+    assert_eq!(unpinned.root_ptr(), root);
+
+    // 旧的根节点已经被释放，读取时返回 StalePage，而不是新 page 的内容
+    for i in 0..300 {
+        assert_eq!(
+            unpinned.get_value(&tree, &key(i)),
+            Err(BTreeError::StalePage)
+        );
+    }
+    let mut iter = unpinned.iter(&tree);
+    assert_eq!(iter.next(), None);
+    assert_eq!(iter.error(), Some(&BTreeError::StalePage));
+}
+
+#[test]
+fn pinned_snapshot_sees_original_after_slots_are_reused() {
+    let db = TempDb::new();
+    let mut tree = db.open();
+    for i in 0..300 {
+        tree.insert(&key(i), &val(i, 0)).unwrap();
+    }
+    tree.commit().unwrap();
+
+    let snapshot = tree.snapshot();
+    let unpinned = tree.snapshot_unpinned();
+    for round in 1..4 {
+        for i in 0..300 {
+            tree.insert(&key(i), &val(i, round)).unwrap();
+        }
+        tree.commit().unwrap();
+    }
+
+    // 被固定的 page 没有被释放，两个 Snapshot 都能读到原来的内容
+    for i in 0..300 {
+        assert_eq!(snapshot.get_value(&tree, &key(i)).unwrap(), Some(val(i, 0)));
+        assert_eq!(unpinned.get_value(&tree, &key(i)).unwrap(), Some(val(i, 0)));
+    }
+    drop(snapshot);
+
+    // 固定结束之后旧的 page 被释放并复用，不会读到其它 key 的 value
+    for i in 0..300 {
+        tree.insert(&key(i), &val(i, 4)).unwrap();
+    }
+    tree.commit().unwrap();
+    for i in 0..300 {
+        match unpinned.get_value(&tree, &key(i)) {
+            Ok(found) => assert_eq!(found, Some(val(i, 0))),
+            Err(err) => assert_eq!(err, BTreeError::StalePage),
+        }
+    }
+}
+
+#[test]
+fn unpinned_snapshot_on_memory_store() {
+    let store = SharedStore::default();
+    let mut tree = BTree::with_store(Box::new(store.clone()));
+    tree.insert(b"a", b"1").unwrap();
+    let snapshot = tree.snapshot_unpinned();
+
+    // 不推迟释放，旧的根节点立即被释放
+    tree.insert(b"a", b"2").unwrap();
+    assert_eq!(store.len(), 1);
+    assert_eq!(snapshot.get_value(&tree, b"a"), Err(BTreeError::StalePage));
+    assert_eq!(tree.get_value(b"a").unwrap(), Some(b"2".to_vec()));
+}
+
+#[test]
+fn generations_are_only_kept_while_snapshots_live() {
+    let mut tree = BTree::with_store(Box::new(SharedStore::default()));
+    for round in 0..5 {
+        for i in 0..200 {
+            tree.insert(&key(i), &val(i, round)).unwrap();
+        }
+    }
+    assert!(tree.generations.is_empty());
+
+    let snapshot = tree.snapshot_unpinned();
+    for i in 0..200 {
+        tree.insert(&key(i), &val(i, 5)).unwrap();
+    }
+    assert!(!tree.generations.is_empty());
+    assert_eq!(
+        snapshot.get_value(&tree, &key(0)),
+        Err(BTreeError::StalePage)
+    );
+
+    // 最后一个 Snapshot 结束之后，下一次写入时清除
+    drop(snapshot);
+    tree.insert(&key(0), &val(0, 6)).unwrap();
+    assert!(tree.generations.is_empty());
+    for i in 0..200 {
+        tree.insert(&key(i), &val(i, 7)).unwrap();
+    }
+    assert!(tree.generations.is_empty());
+}
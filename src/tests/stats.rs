@@ -0,0 +1,48 @@
+use super::util::{key, new_tree};
+use crate::storage::b_tree::{optimal_fanout, optimal_leaf_entries, BTREE_PAGE_SIZE};
+
+#[test]
+fn stats_of_bulk_loaded_tree() {
+    const N: usize = 20_000;
+    let mut tree = new_tree();
+    tree.bulk_load((0..N as u32).map(|i| (key(i), vec![1; 20])))
+        .unwrap();
+    let stats = tree.stats().unwrap();
+
+    // 叶子节点装满时的数量，之后每一层按 fanout 减少
+    let per_leaf = optimal_leaf_entries(BTREE_PAGE_SIZE, 9, 20);
+    let fanout = optimal_fanout(BTREE_PAGE_SIZE, 9, 20);
+    let mut height = 1;
+    let mut width = (N + 1).div_ceil(per_leaf);
+    assert_eq!(stats.leaves, width);
+    while width > 1 {
+        width = width.div_ceil(fanout);
+        height += 1;
+    }
+    assert_eq!(stats.height, height);
+    assert_eq!(stats.levels.len(), height);
+    assert_eq!(stats.levels[0].nodes, 1);
+
+    assert_eq!(stats.nodes, stats.leaves + stats.internal);
+    assert_eq!(stats.nodes, tree.check().unwrap().nodes);
+    let keys: usize = stats.levels.iter().map(|level| level.keys).sum();
+    assert_eq!(stats.avg_keys, keys as f64 / stats.nodes as f64);
+
+    // 除了最后一个，叶子节点都装满了，剩余的空间放不下一个 k-v
+    let leaves = stats.levels.last().unwrap();
+    assert_eq!(leaves.keys, N + 1);
+    let entry = (8 + 2 + 4 + 9 + 20) as f64 / BTREE_PAGE_SIZE as f64;
+    assert!(leaves.max_fill <= 1.0);
+    assert!(leaves.max_fill > 1.0 - entry, "{leaves:?}");
+    assert!(leaves.avg_fill > 0.95, "{leaves:?}");
+    assert!(stats.avg_fill > 0.9, "{stats:?}");
+}
+
+#[test]
+fn stats_of_empty_tree() {
+    let tree = new_tree();
+    let stats = tree.stats().unwrap();
+    assert_eq!(stats.height, 0);
+    assert_eq!(stats.nodes, 0);
+    assert!(stats.levels.is_empty());
+}
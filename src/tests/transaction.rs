@@ -0,0 +1,98 @@
+use super::{file_store::TempDb, shared_store::SharedStore, util::key};
+use crate::storage::b_tree::BTree;
+
+fn val(i: u32) -> Vec<u8> {
+    format!("val{:04}", i).repeat(20).into_bytes()
+}
+
+#[test]
+fn rollback_discards_all_changes() {
+    let store = SharedStore::default();
+    let mut tree = BTree::with_store(Box::new(store.clone()));
+    for i in 0..100 {
+        tree.insert(&key(i), &val(i)).unwrap();
+    }
+    let root = tree.root_ptr();
+    let pages = store.len();
+
+    let mut txn = tree.begin();
+    for i in 100..150 {
+        txn.insert(&key(i), &val(i)).unwrap();
+    }
+    assert!(txn.delete(&key(0)).unwrap());
+    assert_eq!(txn.get_value(&key(120)).unwrap(), Some(val(120)));
+    assert_eq!(txn.get_value(&key(0)).unwrap(), None);
+    txn.rollback();
+
+    assert_eq!(tree.root_ptr(), root);
+    assert_eq!(store.len(), pages);
+    for i in 0..100 {
+        assert_eq!(tree.get_value(&key(i)).unwrap(), Some(val(i)));
+    }
+    for i in 100..150 {
+        assert_eq!(tree.get_value(&key(i)).unwrap(), None);
+    }
+
+    // 未提交就 drop 等同于回滚
+    {
+        let mut txn = tree.begin();
+        txn.insert(b"dropped", b"value").unwrap();
+    }
+    assert_eq!(tree.root_ptr(), root);
+    assert_eq!(tree.get_value(b"dropped").unwrap(), None);
+    assert_eq!(store.len(), pages);
+}
+
+#[test]
+fn commit_publishes_all_changes() {
+    let store = SharedStore::default();
+    let mut tree = BTree::with_store(Box::new(store.clone()));
+    tree.insert(&key(1000), &val(1000)).unwrap();
+
+    let mut txn = tree.begin();
+    for i in 0..50 {
+        txn.insert(&key(i), &val(i)).unwrap();
+    }
+    txn.commit().unwrap();
+
+    for i in 0..50 {
+        assert_eq!(tree.get_value(&key(i)).unwrap(), Some(val(i)));
+    }
+    assert_eq!(tree.len().unwrap(), 51);
+
+    // 旧的 page 在提交时释放，与直接插入的结果相同
+    let direct_store = SharedStore::default();
+    let mut direct = BTree::with_store(Box::new(direct_store.clone()));
+    direct.insert(&key(1000), &val(1000)).unwrap();
+    for i in 0..50 {
+        direct.insert(&key(i), &val(i)).unwrap();
+    }
+    assert_eq!(store.len(), direct_store.len());
+}
+
+#[test]
+fn commit_is_durable_on_file_store() {
+    let db = TempDb::new();
+
+    let mut tree = db.open();
+    let mut txn = tree.begin();
+    for i in 0..50 {
+        txn.insert(&key(i), &val(i)).unwrap();
+    }
+    txn.commit().unwrap();
+
+    let mut txn = tree.begin();
+    for i in 50..100 {
+        txn.insert(&key(i), &val(i)).unwrap();
+    }
+    txn.rollback();
+    drop(tree);
+
+    let tree = db.open();
+    for i in 0..50 {
+        assert_eq!(tree.get_value(&key(i)).unwrap(), Some(val(i)));
+    }
+    for i in 50..100 {
+        assert_eq!(tree.get_value(&key(i)).unwrap(), None);
+    }
+}
@@ -0,0 +1,10 @@
+use crate::storage::{b_tree::BTree, page_store::MemoryStore};
+
+// 定宽的 key，字节序和数值顺序一致
+pub fn key(i: u32) -> Vec<u8> {
+    format!("key{:06}", i).into_bytes()
+}
+
+pub fn new_tree() -> BTree {
+    BTree::with_store(Box::new(MemoryStore::new()))
+}
@@ -0,0 +1,131 @@
+use std::{
+    fs,
+    sync::{Arc, Mutex},
+};
+
+use super::{file_store::TempDb, util::key};
+use crate::storage::{
+    b_tree::{BNode, BTree},
+    error::BTreeError,
+    file_store::FileStore,
+    page_store::PageStore,
+};
+
+// 测试可以直接访问的 FileStore，用于在提交中途模拟崩溃
+#[derive(Clone)]
+struct CrashStore(Arc<Mutex<FileStore>>);
+
+impl PageStore for CrashStore {
+    fn get(&self, ptr: u64) -> Result<BNode, BTreeError> {
+        self.0.lock().unwrap().get(ptr)
+    }
+
+    fn alloc(&mut self, node: &BNode) -> Result<u64, BTreeError> {
+        self.0.lock().unwrap().alloc(node)
+    }
+
+    fn free(&mut self, ptr: u64) {
+        self.0.lock().unwrap().free(ptr)
+    }
+
+    fn root(&self) -> u64 {
+        self.0.lock().unwrap().root()
+    }
+
+    fn commit(&mut self, root: u64) -> Result<(), BTreeError> {
+        self.0.lock().unwrap().commit(root)
+    }
+}
+
+fn val(i: u32) -> Vec<u8> {
+    format!("val{:06}", i).repeat(10).into_bytes()
+}
+
+fn wal_path(db: &TempDb) -> String {
+    format!("{}.wal", db.path.to_string_lossy())
+}
+
+// 写入日志之后、更新 master page 之前崩溃
+fn crash_mid_commit(db: &TempDb) {
+    let mut tree = BTree::with_store(Box::new(FileStore::open_with_wal(&db.path).unwrap()));
+    for i in 0..500 {
+        tree.insert(&key(i), &val(i)).unwrap();
+    }
+    tree.commit().unwrap();
+    drop(tree);
+    assert_eq!(fs::metadata(wal_path(db)).unwrap().len(), 0);
+
+    let store = CrashStore(Arc::new(Mutex::new(
+        FileStore::open_with_wal(&db.path).unwrap(),
+    )));
+    let mut tree = BTree::with_store(Box::new(store.clone()));
+    for i in 0..200 {
+        tree.delete(&key(i)).unwrap();
+    }
+    for i in 1000..1050 {
+        tree.insert(&key(i), &val(i)).unwrap();
+    }
+    store
+        .0
+        .lock()
+        .unwrap()
+        .prepare_commit(tree.root_ptr())
+        .unwrap();
+    assert!(fs::metadata(wal_path(db)).unwrap().len() > 0);
+}
+
+// 重新打开后树的结构完整，并且每个 page 要么可以从根节点到达，要么在 free list 中
+fn reopen_and_check(db: &TempDb) -> BTree {
+    let store = FileStore::open(&db.path).unwrap();
+    let pages = store.page_count() as usize;
+    let free = store.free_count();
+    let tree = BTree::with_store(Box::new(store));
+    let report = tree.check().unwrap();
+    assert_eq!(1 + report.nodes + free, pages);
+    assert_eq!(fs::metadata(wal_path(db)).unwrap().len(), 0);
+    tree
+}
+
+#[test]
+fn complete_wal_record_is_replayed() {
+    let db = TempDb::new();
+    crash_mid_commit(&db);
+
+    let tree = reopen_and_check(&db);
+    assert_eq!(tree.len().unwrap(), 350);
+    assert_eq!(tree.get_value(&key(0)).unwrap(), None);
+    assert_eq!(tree.get_value(&key(200)).unwrap(), Some(val(200)));
+    assert_eq!(tree.get_value(&key(1000)).unwrap(), Some(val(1000)));
+    drop(tree);
+
+    // 重放之后日志已经清空，再次打开的结果相同
+    let tree = reopen_and_check(&db);
+    assert_eq!(tree.len().unwrap(), 350);
+}
+
+#[test]
+fn torn_wal_record_is_rolled_back() {
+    let db = TempDb::new();
+    crash_mid_commit(&db);
+
+    // 日志记录只写入了一部分
+    let file = fs::OpenOptions::new()
+        .write(true)
+        .open(wal_path(&db))
+        .unwrap();
+    let len = file.metadata().unwrap().len();
+    file.set_len(len - 3).unwrap();
+    drop(file);
+
+    let mut tree = reopen_and_check(&db);
+    assert_eq!(tree.len().unwrap(), 500);
+    assert_eq!(tree.get_value(&key(0)).unwrap(), Some(val(0)));
+    assert_eq!(tree.get_value(&key(1000)).unwrap(), None);
+
+    // 回滚之后可以正常提交
+    tree.insert(&key(2000), &val(2000)).unwrap();
+    tree.commit().unwrap();
+    drop(tree);
+    let tree = reopen_and_check(&db);
+    assert_eq!(tree.len().unwrap(), 501);
+}